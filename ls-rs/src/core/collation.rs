@@ -0,0 +1,61 @@
+use std::cmp::Ordering;
+use std::env;
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collation {
+    Bytes,  // C/POSIX locale: plain byte order
+    Locale, // strcoll() under the LC_COLLATE locale
+}
+
+static COLLATION: OnceLock<Collation> = OnceLock::new();
+
+/// Get the collation backend for the current locale environment.
+/// The locale is only set up on the first call.
+pub fn collation() -> Collation {
+    *COLLATION.get_or_init(init_collation)
+}
+
+fn init_collation() -> Collation {
+    if is_c_locale(&collate_locale_name()) {
+        return Collation::Bytes;
+    }
+
+    // Let the C library resolve LC_ALL, LC_COLLATE and LANG itself; this
+    // fails if the requested locale is not installed
+    let empty = CString::default();
+    let result = unsafe { libc::setlocale(libc::LC_COLLATE, empty.as_ptr()) };
+    if result.is_null() {
+        Collation::Bytes
+    } else {
+        Collation::Locale
+    }
+}
+
+/// Name of the locale used for collation, following POSIX precedence
+fn collate_locale_name() -> String {
+    ["LC_ALL", "LC_COLLATE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+fn is_c_locale(name: &str) -> bool {
+    name.is_empty() || name == "C" || name == "POSIX" || name.starts_with("C.")
+}
+
+/// Compare two strings using the given collation backend
+pub fn compare(a: &str, b: &str, collation: Collation) -> Ordering {
+    match collation {
+        Collation::Bytes => a.cmp(b),
+        Collation::Locale => match (CString::new(a), CString::new(b)) {
+            (Ok(a_c), Ok(b_c)) => {
+                let result = unsafe { libc::strcoll(a_c.as_ptr(), b_c.as_ptr()) };
+                result.cmp(&0)
+            }
+            _ => a.cmp(b),
+        },
+    }
+}
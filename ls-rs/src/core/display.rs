@@ -1,6 +1,10 @@
 use crate::core::filesystem::FileInfo;
-use crate::security::permissions;
-use crate::security::selinux::{SELinuxContext, SELinuxExt, SELinuxHandler};
+use crate::security::acl;
+use crate::security::capability;
+use crate::security::inode_flags::{self, InodeFlags};
+use crate::security::permissions::{self, ModeIndicator};
+use crate::security::selinux::{SELinuxContext, SELinuxError, SELinuxExt, SELinuxHandler};
+use crate::security::xattr::{self, XattrConfig};
 use std::io::{self, Write};
 use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::Path;
@@ -9,9 +13,19 @@ use unicode_width::UnicodeWidthStr;
 pub struct DisplayConfig {
     pub term_width: usize,
     pub color_enabled: bool,
+    pub dim_ignored: bool,     // Dim entries ignored by git
+    pub show_git_status: bool, // Show the git status column in long format
     pub use_long_format: bool,
+    pub one_per_line: bool,
     pub human_readable: bool,
     pub selinux_handler: Option<SELinuxHandler>,
+    pub show_acl: bool,              // List POSIX ACL entries in long format
+    pub show_capabilities: bool,     // Show the file capabilities column in long format
+    pub show_inode_flags: bool,      // Show the lsattr flags column in long format
+    pub show_octal_perms: bool,      // Show the octal mode column in long format
+    pub show_access: bool,           // Show the checked user's effective access in long format
+    pub explain_perms: bool,         // Explain who may access each entry in long format
+    pub xattrs: Option<XattrConfig>, // List extended attributes in long format
 }
 
 impl Default for DisplayConfig {
@@ -19,9 +33,19 @@ impl Default for DisplayConfig {
         Self {
             term_width: get_terminal_width(),
             color_enabled: true,
+            dim_ignored: false,
+            show_git_status: false,
             use_long_format: false,
+            one_per_line: false,
             human_readable: false,
             selinux_handler: None,
+            show_acl: false,
+            show_capabilities: false,
+            show_inode_flags: false,
+            show_octal_perms: false,
+            show_access: false,
+            explain_perms: false,
+            xattrs: None,
         }
     }
 }
@@ -29,30 +53,139 @@ impl Default for DisplayConfig {
 pub fn display_entries(entries: &[FileInfo], config: &DisplayConfig) -> io::Result<()> {
     if config.use_long_format {
         display_long_format(entries, config)
+    } else if config.one_per_line {
+        display_one_per_line(entries, config)
     } else {
         display_columns(entries, config)
     }
 }
 
+fn display_one_per_line(entries: &[FileInfo], config: &DisplayConfig) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let labels = context_labels(entries, config);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let label = labels.as_ref().map(|labels| labels[i].as_str());
+        write_labelled_line(&mut handle, entry, label, config)?;
+    }
+
+    Ok(())
+}
+
+/// Write a single entry name on its own line; needs no knowledge of the
+/// other entries, so it can be used while streaming. Contexts are therefore
+/// not padded to a common width.
+pub fn write_entry_line(
+    out: &mut impl Write,
+    entry: &FileInfo,
+    config: &DisplayConfig,
+) -> io::Result<()> {
+    let label = config
+        .selinux_handler
+        .as_ref()
+        .map(|handler| handler.display_context(&lookup_context(entry, handler)));
+    write_labelled_line(out, entry, label.as_deref(), config)
+}
+
+/// Write an entry name, preceded by its context label as GNU `ls -Z` does
+fn write_labelled_line(
+    out: &mut impl Write,
+    entry: &FileInfo,
+    label: Option<&str>,
+    config: &DisplayConfig,
+) -> io::Result<()> {
+    if let Some(label) = label {
+        write!(out, "{} ", label)?;
+    }
+    if config.color_enabled {
+        writeln!(
+            out,
+            "{}{}\x1b[0m",
+            get_entry_color(entry, config),
+            entry.name
+        )
+    } else {
+        writeln!(out, "{}", entry.name)
+    }
+}
+
+/// Look up an entry's SELinux context. Failures other than a missing
+/// context are reported, and the entry is still listed.
+fn lookup_context(
+    entry: &FileInfo,
+    handler: &SELinuxHandler,
+) -> Result<SELinuxContext, SELinuxError> {
+    let context = entry.get_selinux_context(handler);
+    if let Err(err) = &context {
+        if !err.is_absent() {
+            eprintln!("rust-ls: {}: {}", entry.path, err);
+        }
+    }
+    context
+}
+
+/// Context labels of the entries padded to a common width, or `None`
+/// unless contexts are shown
+fn context_labels(entries: &[FileInfo], config: &DisplayConfig) -> Option<Vec<String>> {
+    let handler = config.selinux_handler.as_ref()?;
+    let contexts: Vec<Result<SELinuxContext, SELinuxError>> =
+        entries.iter().map(|e| lookup_context(e, handler)).collect();
+    let width = handler.get_context_width(&contexts);
+
+    Some(
+        contexts
+            .iter()
+            .map(|context| format!("{:<width$}", handler.display_context(context)))
+            .collect(),
+    )
+}
+
 fn display_long_format(entries: &[FileInfo], config: &DisplayConfig) -> io::Result<()> {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
 
-    // Get SELinux contexts if enabled
-    let contexts: Vec<Option<SELinuxContext>> = if let Some(handler) = &config.selinux_handler {
+    let contexts =
+        context_labels(entries, config).unwrap_or_else(|| vec![String::new(); entries.len()]);
+
+    // Like GNU ls, the mode column only grows an eleventh character when some
+    // entry has an ACL or security context
+    let indicators: Vec<ModeIndicator> = entries
+        .iter()
+        .map(|e| permissions::get_mode_indicator(Path::new(&e.path), &e.metadata))
+        .collect();
+    let show_indicators = indicators.iter().any(|i| *i != ModeIndicator::None);
+
+    // getcap-style capability sets; files without any show "-"
+    let capabilities: Vec<String> = if config.show_capabilities {
         entries
             .iter()
-            .map(|e| e.get_selinux_context(handler))
-            .collect::<io::Result<Vec<_>>>()?
+            .map(
+                |e| match capability::read_capabilities(Path::new(&e.path), &e.metadata) {
+                    Ok(Some(caps)) => caps.to_string(),
+                    Ok(None) => "-".to_string(),
+                    Err(err) => format!("<{}>", err),
+                },
+            )
+            .collect()
     } else {
-        vec![None; entries.len()]
+        vec![String::new(); entries.len()]
     };
+    let capability_width = capabilities.iter().map(|c| c.len()).max().unwrap_or(0);
 
-    // Calculate context width if SELinux is enabled
-    let context_width = if let Some(handler) = &config.selinux_handler {
-        handler.get_context_width(&contexts)
+    // Inode flags, unknown where the file system or file type has none; also
+    // read for the immutable highlight when the column is not shown
+    let flags: Vec<Option<InodeFlags>> = if config.show_inode_flags || config.color_enabled {
+        entries
+            .iter()
+            .map(|e| {
+                inode_flags::read_inode_flags(Path::new(&e.path), &e.metadata)
+                    .ok()
+                    .flatten()
+            })
+            .collect()
     } else {
-        0
+        vec![None; entries.len()]
     };
 
     // Calculate field widths
@@ -68,31 +201,84 @@ fn display_long_format(entries: &[FileInfo], config: &DisplayConfig) -> io::Resu
         .max()
         .unwrap_or(0);
 
-    for (entry, context) in entries.iter().zip(contexts.iter()) {
-        let context_str = if let (Some(handler), Some(ctx)) = (&config.selinux_handler, context) {
+    for ((((entry, context), indicator), caps), flags) in entries
+        .iter()
+        .zip(contexts.iter())
+        .zip(&indicators)
+        .zip(&capabilities)
+        .zip(&flags)
+    {
+        let mut mode_str = get_mode_string(&entry.metadata);
+        if show_indicators {
+            mode_str.push(indicator.as_char());
+        }
+        if config.show_octal_perms {
+            mode_str.push(' ');
+            mode_str.push_str(&permissions::get_octal_mode(&entry.metadata));
+        }
+        if let Some(access) = entry.access.filter(|_| config.show_access) {
+            mode_str.push(' ');
+            mode_str.push_str(&access.to_string());
+        }
+
+        let context_str = if config.selinux_handler.is_some() {
+            format!(" {}", context)
+        } else {
+            String::new()
+        };
+
+        // Entries outside a git work tree have no status
+        let git_str = if config.show_git_status {
+            format!(
+                " {}",
+                entry
+                    .git_status
+                    .map(|status| status.column())
+                    .unwrap_or_else(|| "--".to_string())
+            )
+        } else {
+            String::new()
+        };
+
+        let caps_str = if config.show_capabilities {
+            format!(" {:<width$}", caps, width = capability_width)
+        } else {
+            String::new()
+        };
+
+        let flags_str = if config.show_inode_flags {
             format!(
                 " {:<width$}",
-                handler.format_context(ctx),
-                width = context_width
+                flags
+                    .map(|flags| flags.to_string())
+                    .unwrap_or_else(|| "?".to_string()),
+                width = inode_flags::LSATTR_WIDTH
             )
         } else {
             String::new()
         };
 
+        let color = if !config.color_enabled {
+            ""
+        } else if flags.is_some_and(|flags| flags.is_immutable()) {
+            "\x1b[1;31m" // Bold red for immutable files
+        } else {
+            get_entry_color(entry, config)
+        };
+
         writeln!(
             handle,
-            "{} {:>width$} {} {} {:>size_width$} {} {}{}{}{} {}",
-            get_mode_string(&entry.metadata),
+            "{} {:>width$} {} {} {:>size_width$} {}{}{}{} {}{}{}{} {}",
+            mode_str,
             entry.metadata.nlink(),
             get_user_name(entry.metadata.uid()),
             get_group_name(entry.metadata.gid()),
             format_size(entry.metadata.len(), config.human_readable),
             format_time(entry.metadata.modified()?),
-            if config.color_enabled {
-                get_entry_color(entry)
-            } else {
-                ""
-            },
+            git_str,
+            caps_str,
+            flags_str,
+            color,
             entry.name,
             if config.color_enabled { "\x1b[0m" } else { "" },
             context_str,
@@ -104,6 +290,34 @@ fn display_long_format(entries: &[FileInfo], config: &DisplayConfig) -> io::Resu
             width = max_links_width,
             size_width = max_size_width,
         )?;
+
+        if config.explain_perms {
+            let owner = get_user_name(entry.metadata.uid());
+            let group = get_group_name(entry.metadata.gid());
+            for line in permissions::explain_mode(&entry.metadata, &owner, &group) {
+                writeln!(handle, "\t{}", line)?;
+            }
+        }
+
+        if config.show_acl {
+            match acl::read_acls(Path::new(&entry.path), &entry.metadata) {
+                Ok(acls) => {
+                    for line in acl::format_acls(&acls) {
+                        writeln!(handle, "{}", line)?;
+                    }
+                }
+                Err(e) => writeln!(handle, "\t# {}", e)?,
+            }
+        }
+
+        if let Some(xattr_config) = &config.xattrs {
+            // Entries whose attributes cannot be read are listed without them
+            if let Ok(xattrs) = xattr::list_xattrs(Path::new(&entry.path), xattr_config) {
+                for line in xattr::format_xattrs(&xattrs, xattr_config.dump) {
+                    writeln!(handle, "{}", line)?;
+                }
+            }
+        }
     }
 
     Ok(())
@@ -113,8 +327,17 @@ fn display_columns(entries: &[FileInfo], config: &DisplayConfig) -> io::Result<(
     let stdout = io::stdout();
     let mut handle = stdout.lock();
 
-    // Calculate maximum filename width
-    let max_width = entries.iter().map(|e| e.name.width()).max().unwrap_or(0);
+    // With -Z each cell is the context followed by the name
+    let labels = context_labels(entries, config);
+    let cell_width = |index: usize| {
+        let label_width = labels
+            .as_ref()
+            .map_or(0, |labels| labels[index].width() + 1);
+        label_width + entries[index].name.width()
+    };
+
+    // Calculate maximum cell width
+    let max_width = (0..entries.len()).map(cell_width).max().unwrap_or(0);
 
     // Calculate number of columns that will fit
     let column_width = max_width + 2; // Add 2 for spacing
@@ -130,27 +353,31 @@ fn display_columns(entries: &[FileInfo], config: &DisplayConfig) -> io::Result<(
 
             let entry = &entries[index];
             let color = if config.color_enabled {
-                get_entry_color(entry)
+                get_entry_color(entry, config)
             } else {
                 ""
             };
 
-            if col == num_columns - 1 {
-                writeln!(
-                    handle,
-                    "{}{}{}",
-                    color,
-                    entry.name,
-                    if config.color_enabled { "\x1b[0m" } else { "" }
-                )?;
+            if let Some(labels) = &labels {
+                write!(handle, "{} ", labels[index])?;
+            }
+            write!(
+                handle,
+                "{}{}{}",
+                color,
+                entry.name,
+                if config.color_enabled { "\x1b[0m" } else { "" }
+            )?;
+
+            let last = col == num_columns - 1 || (col + 1) * num_rows + row >= entries.len();
+            if last {
+                writeln!(handle)?;
             } else {
                 write!(
                     handle,
-                    "{}{}{:<width$}",
-                    color,
-                    entry.name,
-                    if config.color_enabled { "\x1b[0m" } else { "" },
-                    width = column_width
+                    "{:width$}",
+                    "",
+                    width = column_width - cell_width(index)
                 )?;
             }
         }
@@ -167,9 +394,13 @@ pub fn get_terminal_width() -> usize {
     }
 }
 
-fn get_entry_color(entry: &FileInfo) -> &'static str {
-    if entry.metadata.is_dir() {
+fn get_entry_color(entry: &FileInfo, config: &DisplayConfig) -> &'static str {
+    if config.dim_ignored && entry.git_ignored {
+        "\x1b[2m" // Dim for entries ignored by git
+    } else if entry.metadata.is_dir() {
         "\x1b[34m" // Blue for directories
+    } else if capability::has_capabilities(Path::new(&entry.path), &entry.metadata) {
+        "\x1b[30;41m" // Black on red for file capabilities, as GNU's "ca"
     } else if entry.metadata.permissions().mode() & 0o111 != 0 {
         "\x1b[32m" // Green for executables
     } else {
@@ -228,6 +459,11 @@ fn format_size(size: u64, human_readable: bool) -> String {
 }
 
 impl DisplayConfig {
+    /// Whether entries can be written as soon as they are read
+    pub fn streams_output(&self) -> bool {
+        self.one_per_line && !self.use_long_format
+    }
+
     pub fn with_selinux(mut self, selinux_handler: Option<SELinuxHandler>) -> Self {
         self.selinux_handler = selinux_handler;
         self
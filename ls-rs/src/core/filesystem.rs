@@ -1,6 +1,11 @@
+use crate::core::collation::{self, Collation};
 use crate::core::display::DisplayConfig;
-use crate::core::sorting::{SortKey, SortOptions};
-use crate::security::selinux::{SELinuxContext, SELinuxHandler};
+use crate::core::git::{GitStatus, GitStatuses};
+use crate::core::sorting::{SortField, SortKey, SortOptions};
+use crate::options::filtering::{filter_entries, FilterOptions};
+use crate::options::gitignore::GitIgnore;
+use crate::security::access::{Access, AccessChecker};
+use crate::security::selinux::{SELinuxContext, SELinuxError, SELinuxHandler};
 use crate::{Args, SortBy};
 use std::fs::{self, DirEntry};
 use std::io;
@@ -11,62 +16,211 @@ pub struct FileInfo {
     pub name: String,
     pub metadata: fs::Metadata,
     pub path: String,
+    pub git_ignored: bool,
+    pub git_status: Option<GitStatus>,
+    pub access: Option<Access>, // What the checked user may do with the entry
 }
 
-pub fn list_directory(path: &str, args: &Args, config: &DisplayConfig) -> io::Result<()> {
+/// Git and access information for one listed directory, attached to its
+/// entries before they are filtered
+struct Annotations {
+    gitignore: Option<GitIgnore>,
+    statuses: Option<GitStatuses>,
+    access: Option<AccessChecker>,
+}
+
+impl Annotations {
+    fn load(path: &Path, args: &Args, filter: &FilterOptions, config: &DisplayConfig) -> Self {
+        Self {
+            gitignore: if filter.needs_gitignore() {
+                GitIgnore::for_directory(path)
+            } else {
+                None
+            },
+            statuses: if filter.needs_git_status() || config.show_git_status {
+                GitStatuses::for_directory(path)
+            } else {
+                None
+            },
+            access: if filter.needs_access() || config.show_access {
+                Some(AccessChecker::new(args.as_user))
+            } else {
+                None
+            },
+        }
+    }
+
+    fn annotate(&self, entry: &mut FileInfo) {
+        if let Some(gitignore) = &self.gitignore {
+            entry.git_ignored = gitignore.is_ignored(&entry.name, entry.metadata.is_dir());
+        }
+        if let Some(statuses) = &self.statuses {
+            entry.git_status = Some(statuses.status_of(&entry.name));
+        }
+        if let Some(checker) = &self.access {
+            entry.access = Some(checker.check(Path::new(&entry.path), &entry.metadata));
+        }
+    }
+}
+
+pub fn list_directory(
+    path: &str,
+    args: &Args,
+    filter: &FilterOptions,
+    config: &DisplayConfig,
+) -> io::Result<()> {
     let path = Path::new(path);
-    let mut entries = collect_entries(path, args)?;
 
     let sort_options = SortOptions {
-        key: match args.sort {
-            SortBy::Name => SortKey::Name,
-            SortBy::Size => SortKey::Size,
-            SortBy::Time => SortKey::Time,
-            SortBy::Extension => SortKey::Extension,
-            SortBy::Type => SortKey::Type,
-            SortBy::Owner => SortKey::Owner,
-            SortBy::Group => SortKey::Group,
-            SortBy::None => SortKey::None,
-        },
+        keys: args
+            .sort
+            .iter()
+            .map(|spec| SortField {
+                key: sort_key(spec.by),
+                descending: spec.descending,
+            })
+            .collect(),
         reverse: args.reverse,
         dirs_first: args.dirs_first,
         case_sensitive: args.case_sensitive,
         numeric_sort: false,
-        version_sort: false,
-        locale_sort: false,
+        version_sort: args.version_sort || args.sort.iter().any(|spec| spec.by == SortBy::Version),
+        locale_sort: collation::collation() == Collation::Locale,
     };
 
+    // Unsorted one-per-line output needs no buffering, so print entries as
+    // readdir returns them
+    let unsorted = sort_options
+        .keys
+        .iter()
+        .all(|field| matches!(field.key, SortKey::None));
+    let annotations = Annotations::load(path, args, filter, config);
+    if unsorted && !sort_options.dirs_first && config.streams_output() {
+        return stream_entries(path, args.all, &annotations, filter, config);
+    }
+
+    let mut entries = collect_entries(path, args.all, filter)?;
+    for entry in &mut entries {
+        annotations.annotate(entry);
+    }
+    let mut entries = filter_entries(entries, filter);
+
     crate::core::sorting::sort_entries(&mut entries, &sort_options);
 
     crate::core::display::display_entries(&entries, config)
 }
 
-fn collect_entries(path: &Path, args: &Args) -> io::Result<Vec<FileInfo>> {
-    let mut entries = Vec::new();
-    let dir = fs::read_dir(path)?;
+fn stream_entries(
+    path: &Path,
+    include_dots: bool,
+    annotations: &Annotations,
+    filter: &FilterOptions,
+    config: &DisplayConfig,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
 
-    for entry in dir {
-        let entry = entry?;
-        let name = entry.file_name().to_string_lossy().into_owned();
+    let dots = if include_dots {
+        dot_entries(path, filter)?
+    } else {
+        Vec::new()
+    };
+    let entries = fs::read_dir(path)?.filter_map(|entry| match entry {
+        Ok(entry) if filter.excludes_name(&entry.file_name().to_string_lossy()) => None,
+        entry => Some(entry.and_then(read_entry)),
+    });
+    let filter = filter.to_expr();
 
-        // Skip hidden files unless -a flag is present
-        if !args.all && name.starts_with('.') {
-            continue;
+    for info in dots.into_iter().map(Ok).chain(entries) {
+        let mut info = info?;
+        annotations.annotate(&mut info);
+        if filter.evaluate(&info) {
+            crate::core::display::write_entry_line(&mut handle, &info, config)?;
         }
+    }
+
+    Ok(())
+}
 
-        let metadata = entry.metadata()?;
-        let path = entry.path().to_string_lossy().into_owned();
+fn sort_key(by: SortBy) -> SortKey {
+    match by {
+        SortBy::Name => SortKey::Name,
+        SortBy::Size => SortKey::Size,
+        SortBy::Time => SortKey::Time,
+        SortBy::Extension => SortKey::Extension,
+        SortBy::Type => SortKey::Type,
+        SortBy::Owner => SortKey::Owner,
+        SortBy::Group => SortKey::Group,
+        SortBy::Version => SortKey::Name,
+        SortBy::Permissions => SortKey::Permissions,
+        SortBy::Inode => SortKey::Inode,
+        SortBy::Blocks => SortKey::Blocks,
+        SortBy::Links => SortKey::Links,
+        SortBy::NameLength => SortKey::NameLength,
+        SortBy::Birth => SortKey::Birth,
+        SortBy::Access => SortKey::Access,
+        SortBy::None => SortKey::None,
+    }
+}
+
+/// Read the entries of a directory, skipping those the name filters exclude
+/// before they are stat'ed
+fn collect_entries(
+    path: &Path,
+    include_dots: bool,
+    filter: &FilterOptions,
+) -> io::Result<Vec<FileInfo>> {
+    let mut entries = if include_dots {
+        dot_entries(path, filter)?
+    } else {
+        Vec::new()
+    };
+    let dir = fs::read_dir(path)?;
 
-        entries.push(FileInfo {
-            name,
-            metadata,
-            path,
-        });
+    for entry in dir {
+        let entry = entry?;
+        if !filter.excludes_name(&entry.file_name().to_string_lossy()) {
+            entries.push(read_entry(entry)?);
+        }
     }
 
     Ok(entries)
 }
 
+/// Build the `.` and `..` entries, which `read_dir` never returns
+fn dot_entries(path: &Path, filter: &FilterOptions) -> io::Result<Vec<FileInfo>> {
+    [".", ".."]
+        .iter()
+        .filter(|name| !filter.excludes_name(name))
+        .map(|name| {
+            let path = path.join(name);
+            Ok(FileInfo {
+                name: name.to_string(),
+                metadata: fs::metadata(&path)?,
+                path: path.to_string_lossy().into_owned(),
+                git_ignored: false,
+                git_status: None,
+                access: None,
+            })
+        })
+        .collect()
+}
+
+fn read_entry(entry: DirEntry) -> io::Result<FileInfo> {
+    let name = entry.file_name().to_string_lossy().into_owned();
+    let metadata = entry.metadata()?;
+    let path = entry.path().to_string_lossy().into_owned();
+
+    Ok(FileInfo {
+        name,
+        metadata,
+        path,
+        git_ignored: false,
+        git_status: None,
+        access: None,
+    })
+}
+
 fn display_long_format(entries: &[FileInfo]) -> io::Result<()> {
     for entry in entries {
         println!(
@@ -117,7 +271,7 @@ impl FileInfo {
     pub fn get_selinux_context(
         &self,
         handler: &SELinuxHandler,
-    ) -> io::Result<Option<SELinuxContext>> {
+    ) -> Result<SELinuxContext, SELinuxError> {
         handler.get_context(Path::new(&self.path))
     }
 }
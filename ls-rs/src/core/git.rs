@@ -0,0 +1,219 @@
+use git2::{Repository, Status, StatusOptions};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// State of an entry in the index or in the work tree, with the letters
+/// used by `git status --short`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GitState {
+    #[default]
+    Unmodified,
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChange,
+    Untracked,
+    Ignored,
+    Conflicted,
+}
+
+impl GitState {
+    pub fn as_char(&self) -> char {
+        match self {
+            GitState::Unmodified => '-',
+            GitState::Added => 'A',
+            GitState::Modified => 'M',
+            GitState::Deleted => 'D',
+            GitState::Renamed => 'R',
+            GitState::TypeChange => 'T',
+            GitState::Untracked => '?',
+            GitState::Ignored => '!',
+            GitState::Conflicted => 'U',
+        }
+    }
+
+    /// Which state wins when aggregating the contents of a directory
+    fn rank(&self) -> u8 {
+        match self {
+            GitState::Unmodified => 0,
+            GitState::Ignored => 1,
+            GitState::Untracked => 2,
+            GitState::TypeChange => 3,
+            GitState::Renamed => 4,
+            GitState::Deleted => 5,
+            GitState::Added => 6,
+            GitState::Modified => 7,
+            GitState::Conflicted => 8,
+        }
+    }
+
+    fn max(self, other: GitState) -> GitState {
+        if other.rank() > self.rank() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn is_change(&self) -> bool {
+        !matches!(
+            self,
+            GitState::Unmodified | GitState::Untracked | GitState::Ignored
+        )
+    }
+}
+
+/// Index and work tree status of an entry
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GitStatus {
+    pub staged: GitState,
+    pub unstaged: GitState,
+}
+
+impl GitStatus {
+    fn from_status(status: Status) -> Self {
+        if status.is_conflicted() {
+            return Self {
+                staged: GitState::Conflicted,
+                unstaged: GitState::Conflicted,
+            };
+        }
+
+        let staged = if status.is_index_new() {
+            GitState::Added
+        } else if status.is_index_modified() {
+            GitState::Modified
+        } else if status.is_index_deleted() {
+            GitState::Deleted
+        } else if status.is_index_renamed() {
+            GitState::Renamed
+        } else if status.is_index_typechange() {
+            GitState::TypeChange
+        } else {
+            GitState::Unmodified
+        };
+
+        let unstaged = if status.is_ignored() {
+            GitState::Ignored
+        } else if status.is_wt_new() {
+            GitState::Untracked
+        } else if status.is_wt_modified() {
+            GitState::Modified
+        } else if status.is_wt_deleted() {
+            GitState::Deleted
+        } else if status.is_wt_renamed() {
+            GitState::Renamed
+        } else if status.is_wt_typechange() {
+            GitState::TypeChange
+        } else {
+            GitState::Unmodified
+        };
+
+        Self { staged, unstaged }
+    }
+
+    fn merge(self, other: GitStatus) -> GitStatus {
+        GitStatus {
+            staged: self.staged.max(other.staged),
+            unstaged: self.unstaged.max(other.unstaged),
+        }
+    }
+
+    /// Changed in the index or the work tree, not counting untracked files
+    pub fn is_modified(&self) -> bool {
+        self.staged.is_change() || self.unstaged.is_change()
+    }
+
+    pub fn is_untracked(&self) -> bool {
+        self.unstaged == GitState::Untracked
+    }
+
+    /// Two-letter column, e.g. `-M` or `A-`
+    pub fn column(&self) -> String {
+        format!("{}{}", self.staged.as_char(), self.unstaged.as_char())
+    }
+}
+
+/// Statuses of everything below one listed directory of a work tree
+pub struct GitStatuses {
+    relative_dir: String, // Listed directory, relative to the work tree root
+    statuses: BTreeMap<String, GitStatus>,
+}
+
+impl GitStatuses {
+    /// Read the statuses for a directory from its local repository, or
+    /// `None` if it is not inside a git work tree
+    pub fn for_directory(dir: &Path) -> Option<Self> {
+        let dir = fs::canonicalize(dir).ok()?;
+        let repo = Repository::discover(&dir).ok()?;
+        let workdir = fs::canonicalize(repo.workdir()?).ok()?;
+        let relative_dir = dir
+            .strip_prefix(&workdir)
+            .ok()?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(true)
+            .recurse_ignored_dirs(false);
+        if !relative_dir.is_empty() {
+            // Match the directory literally; its name may contain glob characters
+            options.pathspec(&relative_dir).disable_pathspec_match(true);
+        }
+
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .ok()?
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.path()?.to_string();
+                Some((path, GitStatus::from_status(entry.status())))
+            })
+            .collect();
+
+        Some(Self {
+            relative_dir,
+            statuses,
+        })
+    }
+
+    /// Status of the entry `name` of the listed directory; directories
+    /// aggregate the status of their contents
+    pub fn status_of(&self, name: &str) -> GitStatus {
+        let relative = match name {
+            ".." => return GitStatus::default(),
+            "." => self.relative_dir.clone(),
+            _ if self.relative_dir.is_empty() => name.to_string(),
+            _ => format!("{}/{}", self.relative_dir, name),
+        };
+
+        // Entries inside a directory that git reports as a whole, such as an
+        // ignored one, share its status
+        for ancestor in Path::new(&relative).ancestors().skip(1) {
+            let key = format!("{}/", ancestor.to_string_lossy());
+            if let Some(status) = self.statuses.get(&key) {
+                return *status;
+            }
+        }
+
+        let mut status = self.statuses.get(&relative).copied().unwrap_or_default();
+        let dir_prefix = if relative.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", relative)
+        };
+        for (_, contained) in self
+            .statuses
+            .range(dir_prefix.clone()..)
+            .take_while(|(path, _)| path.starts_with(&dir_prefix))
+        {
+            status = status.merge(*contained);
+        }
+        status
+    }
+}
@@ -1,3 +1,5 @@
+pub mod collation;
 pub mod display;
 pub mod filesystem;
+pub mod git;
 pub mod sorting;
@@ -1,6 +1,10 @@
+use crate::core::collation::{self, Collation};
 use crate::core::filesystem::FileInfo;
 use std::cmp::Ordering;
+use std::fs;
 use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Debug, Clone, Copy)]
 pub enum SortKey {
@@ -13,12 +17,33 @@ pub enum SortKey {
     Group,       // Sort by file group
     Permissions, // Sort by file permissions
     Inode,       // Sort by inode number
+    Blocks,      // Sort by allocated size
+    Links,       // Sort by hard link count
+    NameLength,  // Sort by display width of the filename
+    Birth,       // Sort by creation (birth) time
+    Access,      // Sort by access time
     None,        // No sorting
 }
 
+/// A single sort key with its own direction
+#[derive(Debug, Clone, Copy)]
+pub struct SortField {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl SortField {
+    pub fn new(key: SortKey) -> Self {
+        Self {
+            key,
+            descending: false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SortOptions {
-    pub key: SortKey,
+    pub keys: Vec<SortField>, // Sort keys, in order of precedence
     pub reverse: bool,        // Reverse the sort order
     pub dirs_first: bool,     // List directories before files
     pub case_sensitive: bool, // Use case-sensitive sorting
@@ -30,7 +55,7 @@ pub struct SortOptions {
 impl Default for SortOptions {
     fn default() -> Self {
         Self {
-            key: SortKey::Name,
+            keys: vec![SortField::new(SortKey::Name)],
             reverse: false,
             dirs_first: false,
             case_sensitive: true,
@@ -42,42 +67,109 @@ impl Default for SortOptions {
 }
 
 pub fn sort_entries(entries: &mut Vec<FileInfo>, options: &SortOptions) {
-    entries.sort_by(|a, b| {
-        // Handle directories first if enabled
-        if options.dirs_first {
-            match (a.metadata.is_dir(), b.metadata.is_dir()) {
-                (true, false) => return Ordering::Less,
-                (false, true) => return Ordering::Greater,
-                _ => {}
-            }
-        }
+    if !options.dirs_first {
+        sort_group(entries, options);
+        return;
+    }
 
-        let mut ordering = match options.key {
-            SortKey::Name => compare_names(a, b, options.case_sensitive),
-            SortKey::Size => compare_sizes(a, b),
-            SortKey::Time => compare_times(a, b),
-            SortKey::Extension => compare_extensions(a, b, options.case_sensitive),
-            SortKey::Type => compare_types(a, b),
-            SortKey::Owner => compare_owners(a, b),
-            SortKey::Group => compare_groups(a, b),
-            SortKey::Permissions => compare_permissions(a, b),
-            SortKey::Inode => compare_inodes(a, b),
-            SortKey::None => Ordering::Equal,
-        };
+    // Directories, including symlinks to directories, form their own group
+    // that stays first even when the order is reversed
+    let (mut dirs, mut others): (Vec<FileInfo>, Vec<FileInfo>) =
+        entries.drain(..).partition(is_directory);
+    sort_group(&mut dirs, options);
+    sort_group(&mut others, options);
+
+    entries.append(&mut dirs);
+    entries.append(&mut others);
+}
+
+fn sort_group(entries: &mut [FileInfo], options: &SortOptions) {
+    // Leave entries in directory order when no real key was requested
+    if options
+        .keys
+        .iter()
+        .all(|field| matches!(field.key, SortKey::None))
+    {
+        return;
+    }
+
+    entries.sort_by(|a, b| {
+        // Apply each key in turn, breaking remaining ties by name so the
+        // output is reproducible
+        let ordering = options
+            .keys
+            .iter()
+            .map(|field| compare_by_field(a, b, field, options))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| compare_names(a, b, options).then_with(|| a.name.cmp(&b.name)));
 
         if options.reverse {
-            ordering = ordering.reverse();
+            ordering.reverse()
+        } else {
+            ordering
         }
+    });
+}
 
+fn is_directory(entry: &FileInfo) -> bool {
+    entry.metadata.is_dir()
+        || (entry.metadata.file_type().is_symlink()
+            && fs::metadata(&entry.path).is_ok_and(|target| target.is_dir()))
+}
+
+fn compare_by_field(
+    a: &FileInfo,
+    b: &FileInfo,
+    field: &SortField,
+    options: &SortOptions,
+) -> Ordering {
+    let ordering = match field.key {
+        SortKey::Name => compare_names(a, b, options),
+        SortKey::Size => compare_sizes(a, b),
+        SortKey::Time => compare_times(a, b),
+        SortKey::Extension => compare_extensions(a, b, options),
+        SortKey::Type => compare_types(a, b),
+        SortKey::Owner => compare_owners(a, b),
+        SortKey::Group => compare_groups(a, b),
+        SortKey::Permissions => compare_permissions(a, b),
+        SortKey::Inode => compare_inodes(a, b),
+        SortKey::Blocks => compare_blocks(a, b),
+        SortKey::Links => compare_links(a, b),
+        SortKey::NameLength => compare_name_lengths(a, b),
+        SortKey::Birth => compare_birth_times(a, b),
+        SortKey::Access => compare_access_times(a, b),
+        SortKey::None => Ordering::Equal,
+    };
+
+    if field.descending {
+        ordering.reverse()
+    } else {
         ordering
-    });
+    }
 }
 
-fn compare_names(a: &FileInfo, b: &FileInfo, case_sensitive: bool) -> Ordering {
-    if case_sensitive {
-        a.name.cmp(&b.name)
+fn compare_names(a: &FileInfo, b: &FileInfo, options: &SortOptions) -> Ordering {
+    compare_strings(&a.name, &b.name, options)
+}
+
+/// Compare two strings using the name comparator selected by `options`
+fn compare_strings(a: &str, b: &str, options: &SortOptions) -> Ordering {
+    if options.version_sort {
+        // Like GNU ls, versions compare the raw bytes regardless of case and
+        // fall back to byte order when they compare equal
+        version_compare(a, b).then_with(|| a.cmp(b))
+    } else if options.case_sensitive {
+        compare_with(a, b, options)
     } else {
-        a.name.to_lowercase().cmp(&b.name.to_lowercase())
+        compare_with(&a.to_lowercase(), &b.to_lowercase(), options)
+    }
+}
+
+fn compare_with(a: &str, b: &str, options: &SortOptions) -> Ordering {
+    if options.locale_sort {
+        collation::compare(a, b, Collation::Locale).then_with(|| a.cmp(b))
+    } else {
+        a.cmp(b)
     }
 }
 
@@ -89,22 +181,26 @@ fn compare_times(a: &FileInfo, b: &FileInfo) -> Ordering {
     a.metadata.modified().ok().cmp(&b.metadata.modified().ok())
 }
 
-fn compare_extensions(a: &FileInfo, b: &FileInfo, case_sensitive: bool) -> Ordering {
-    let ext_a = std::path::Path::new(&a.name)
+fn compare_birth_times(a: &FileInfo, b: &FileInfo) -> Ordering {
+    a.metadata.created().ok().cmp(&b.metadata.created().ok())
+}
+
+fn compare_access_times(a: &FileInfo, b: &FileInfo) -> Ordering {
+    a.metadata.accessed().ok().cmp(&b.metadata.accessed().ok())
+}
+
+fn compare_extensions(a: &FileInfo, b: &FileInfo, options: &SortOptions) -> Ordering {
+    let ext_a = Path::new(&a.name)
         .extension()
         .and_then(|e| e.to_str())
         .unwrap_or("");
 
-    let ext_b = std::path::Path::new(&b.name)
+    let ext_b = Path::new(&b.name)
         .extension()
         .and_then(|e| e.to_str())
         .unwrap_or("");
 
-    if case_sensitive {
-        ext_a.cmp(ext_b)
-    } else {
-        ext_a.to_lowercase().cmp(&ext_b.to_lowercase())
-    }
+    compare_strings(ext_a, ext_b, options)
 }
 
 fn get_file_type(metadata: &std::fs::Metadata) -> char {
@@ -146,3 +242,145 @@ fn compare_permissions(a: &FileInfo, b: &FileInfo) -> Ordering {
 fn compare_inodes(a: &FileInfo, b: &FileInfo) -> Ordering {
     a.metadata.ino().cmp(&b.metadata.ino())
 }
+
+fn compare_blocks(a: &FileInfo, b: &FileInfo) -> Ordering {
+    a.metadata.blocks().cmp(&b.metadata.blocks())
+}
+
+fn compare_links(a: &FileInfo, b: &FileInfo) -> Ordering {
+    a.metadata.nlink().cmp(&b.metadata.nlink())
+}
+
+fn compare_name_lengths(a: &FileInfo, b: &FileInfo) -> Ordering {
+    a.name.width().cmp(&b.name.width())
+}
+
+/// Compare two file names with GNU `filevercmp` semantics, so that `file2`
+/// sorts before `file10` and `libfoo-1.9.so` before `libfoo-1.10.so`
+pub fn version_compare(a: &str, b: &str) -> Ordering {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+
+    // Empty names sort first
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        _ => {}
+    }
+
+    // "." sorts first, then "..", then other hidden names, then everything else
+    match (a[0] == b'.', b[0] == b'.') {
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        (true, true) => {
+            for special in [&b"."[..], &b".."[..]] {
+                match (a == special, b == special) {
+                    (true, true) => return Ordering::Equal,
+                    (true, false) => return Ordering::Less,
+                    (false, true) => return Ordering::Greater,
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    // Compare without file suffixes first, then with them if still equal
+    let a_prefix = file_prefix_len(a);
+    let b_prefix = file_prefix_len(b);
+
+    let result = version_rev_compare(&a[..a_prefix], &b[..b_prefix]);
+    if result != Ordering::Equal || (a_prefix == a.len() && b_prefix == b.len()) {
+        result
+    } else {
+        version_rev_compare(a, b)
+    }
+}
+
+/// Length of `name` without its trailing suffix, matching the regex
+/// `(\.[A-Za-z~][A-Za-z0-9~]*)*$`. The first byte is never part of the suffix.
+fn file_prefix_len(name: &[u8]) -> usize {
+    let len = name.len();
+    let mut prefix_len = 0;
+    let mut i = 0;
+
+    while i < len {
+        i += 1;
+        prefix_len = i;
+        while i + 1 < len
+            && name[i] == b'.'
+            && (name[i + 1].is_ascii_alphabetic() || name[i + 1] == b'~')
+        {
+            i += 2;
+            while i < len && (name[i].is_ascii_alphanumeric() || name[i] == b'~') {
+                i += 1;
+            }
+        }
+    }
+
+    prefix_len
+}
+
+/// Sort weight of the byte at `pos`: '~' weighs least, so `1.0~rc1` sorts
+/// before `1.0`, then the end of the string, digits, letters, and everything
+/// else
+fn version_order(s: &[u8], pos: usize) -> i32 {
+    match s.get(pos) {
+        None => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+        Some(b'~') => -2,
+        Some(c) => i32::from(*c) + 256,
+    }
+}
+
+/// The Debian version comparison algorithm used by `filevercmp`
+fn version_rev_compare(a: &[u8], b: &[u8]) -> Ordering {
+    let is_digit = |s: &[u8], pos: usize| s.get(pos).is_some_and(u8::is_ascii_digit);
+    let mut a_pos = 0;
+    let mut b_pos = 0;
+
+    while a_pos < a.len() || b_pos < b.len() {
+        // Non-digit run
+        while (a_pos < a.len() && !is_digit(a, a_pos)) || (b_pos < b.len() && !is_digit(b, b_pos)) {
+            let a_order = version_order(a, a_pos);
+            let b_order = version_order(b, b_pos);
+            if a_order != b_order {
+                return a_order.cmp(&b_order);
+            }
+            a_pos += 1;
+            b_pos += 1;
+        }
+
+        // Numeric run, ignoring leading zeros
+        while a.get(a_pos) == Some(&b'0') {
+            a_pos += 1;
+        }
+        while b.get(b_pos) == Some(&b'0') {
+            b_pos += 1;
+        }
+
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, a_pos) && is_digit(b, b_pos) {
+            if first_diff == Ordering::Equal {
+                first_diff = a[a_pos].cmp(&b[b_pos]);
+            }
+            a_pos += 1;
+            b_pos += 1;
+        }
+
+        // The longer number is the larger one
+        if is_digit(a, a_pos) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, b_pos) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+
+    Ordering::Equal
+}
@@ -3,6 +3,9 @@ mod options;
 mod security;
 
 use clap::{Parser, ValueEnum};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -15,26 +18,172 @@ struct Args {
     #[arg(short = 'l', long)]
     long: bool,
 
-    /// Show hidden files
+    /// Show hidden files, including . and ..
     #[arg(short = 'a', long = "all")]
     all: bool,
 
-    /// Sort by (name, size, time, extension, type, owner, group)
-    #[arg(short = 's', long = "sort", value_enum, default_value = "name")]
-    sort: SortBy,
+    /// Show hidden files, except . and ..
+    #[arg(short = 'A', long = "almost-all")]
+    almost_all: bool,
 
-    /// Sort directories before files
-    #[arg(short = 'd', long = "dirs-first")]
+    /// Do not list entries matching a glob pattern
+    #[arg(short = 'I', long = "ignore", value_name = "PATTERN", value_parser = options::filtering::parse_glob)]
+    ignore: Vec<options::filtering::GlobPattern>,
+
+    /// Do not list entries matching a glob pattern (overridden by -a or -A)
+    #[arg(long = "hide", value_name = "PATTERN", value_parser = options::filtering::parse_glob)]
+    hide: Vec<options::filtering::GlobPattern>,
+
+    /// Do not list backup entries ending with ~
+    #[arg(short = 'B', long = "ignore-backups")]
+    ignore_backups: bool,
+
+    /// Sort by comma-separated keys (name, size, time, extension, type, owner, group, version,
+    /// permissions, inode, blocks, links, name-length, birth, access), each optionally
+    /// suffixed with :asc or :desc
+    #[arg(
+        short = 's',
+        long = "sort",
+        value_parser = parse_sort_spec,
+        value_delimiter = ',',
+        default_value = "name"
+    )]
+    sort: Vec<SortSpec>,
+
+    /// Group directories (and symlinks to directories) before files, even when reversed
+    #[arg(
+        short = 'd',
+        long = "dirs-first",
+        visible_alias = "group-directories-first"
+    )]
     dirs_first: bool,
 
+    /// Only show entries whose name matches a glob pattern, e.g. '*.{rs,toml}'
+    #[arg(long = "glob", value_parser = options::filtering::parse_glob)]
+    glob: Option<options::filtering::GlobPattern>,
+
+    /// Like --glob, but case-insensitive
+    #[arg(long = "iglob", conflicts_with = "glob", value_parser = options::filtering::parse_iglob)]
+    iglob: Option<options::filtering::GlobPattern>,
+
+    /// Only show entries whose name matches a regular expression
+    #[arg(long = "regex", value_parser = Regex::new)]
+    regex: Option<Regex>,
+
+    /// Like --regex, but case-insensitive
+    #[arg(long = "iregex", conflicts_with = "regex", value_parser = options::filtering::parse_iregex)]
+    iregex: Option<Regex>,
+
+    /// Only show entries of at least this size (e.g. 512, 10K, 2G)
+    #[arg(long = "min-size", value_parser = options::filtering::parse_size)]
+    min_size: Option<u64>,
+
+    /// Only show entries of at most this size (e.g. 512, 10K, 2G)
+    #[arg(long = "max-size", value_parser = options::filtering::parse_size)]
+    max_size: Option<u64>,
+
+    /// Only show entries of these types (f, d, l, s, p, b, c)
+    #[arg(long = "type", value_enum, value_delimiter = ',')]
+    types: Vec<EntryType>,
+
+    /// Only show entries newer than a time (e.g. 2h, 7d, 2024-01-31 12:00)
+    #[arg(long = "newer", value_name = "TIME", value_parser = options::filtering::parse_time)]
+    newer: Option<SystemTime>,
+
+    /// Only show entries older than a time (e.g. 2h, 7d, 2024-01-31 12:00)
+    #[arg(long = "older", value_name = "TIME", value_parser = options::filtering::parse_time)]
+    older: Option<SystemTime>,
+
+    /// Only show entries newer than the given reference file
+    #[arg(long = "newer-than", value_name = "FILE")]
+    newer_than: Option<PathBuf>,
+
+    /// Timestamp used by the time filters
+    #[arg(long = "time-field", value_enum, default_value = "mtime")]
+    time_field: TimeFieldArg,
+
+    /// Only show entries owned by a user (name or UID)
+    #[arg(long = "user", value_parser = options::filtering::parse_user)]
+    user: Option<u32>,
+
+    /// Only show entries owned by a group (name or GID)
+    #[arg(long = "group", value_parser = options::filtering::parse_group)]
+    group: Option<u32>,
+
+    /// Only show entries whose owner UID is in a range (e.g. 1000-60000, -999)
+    #[arg(long = "uid-range", value_name = "MIN-MAX", allow_hyphen_values = true, value_parser = options::filtering::parse_uid_range)]
+    uid_range: Option<(u32, u32)>,
+
+    /// Only show entries with matching permission bits, as with find -perm:
+    /// MODE (exactly), -MODE (all bits set) or /MODE (any bit set)
+    #[arg(long = "perm", value_name = "MODE", allow_hyphen_values = true, value_parser = options::filtering::parse_perm)]
+    perm: Option<options::filtering::PermMatch>,
+
+    /// Only show entries matching a filter expression,
+    /// e.g. 'size > 10M and (ext == "log" or mtime < 2d) and not hidden'
+    #[arg(long = "where", value_name = "EXPR", value_parser = options::expression::parse_filter)]
+    where_expr: Option<options::expression::Expr>,
+
+    /// Apply .gitignore rules: hide ignored entries, or dim them
+    #[arg(long = "gitignore", value_name = "MODE", value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "hide")]
+    gitignore: Option<GitIgnoreArg>,
+
+    /// Only show entries ignored by git
+    #[arg(long = "git-ignored")]
+    git_ignored: bool,
+
+    /// Show each entry's git index and work tree status in long format
+    #[arg(long = "git")]
+    git: bool,
+
+    /// Only show entries changed in the git index or work tree
+    #[arg(long = "git-modified")]
+    git_modified: bool,
+
+    /// Only show entries not tracked by git
+    #[arg(long = "git-untracked")]
+    git_untracked: bool,
+
+    /// Only show entries the user has all of these rights on (letters from rwx)
+    #[arg(long = "can", value_name = "RIGHTS", value_parser = security::access::parse_access)]
+    can: Option<security::access::Access>,
+
+    /// Only show entries the user has none of these rights on, e.g. --cannot r
+    #[arg(long = "cannot", value_name = "RIGHTS", value_parser = security::access::parse_access)]
+    cannot: Option<security::access::Access>,
+
+    /// Check access for this user (name or UID) instead of the invoking one
+    #[arg(long = "as-user", value_name = "USER", value_parser = options::filtering::parse_user)]
+    as_user: Option<u32>,
+
+    /// Only show files with file capabilities
+    #[arg(long = "has-caps")]
+    has_caps: bool,
+
     /// Use case-sensitive sorting
     #[arg(long = "case-sensitive")]
     case_sensitive: bool,
 
+    /// Natural sort of (version) numbers within names
+    #[arg(short = 'v', long = "version-sort")]
+    version_sort: bool,
+
     /// Reverse sort order
     #[arg(short = 'r', long = "reverse")]
     reverse: bool,
 
+    /// Do not sort; list entries in directory order as they are read
+    #[arg(short = 'U')]
+    unsorted: bool,
+
+    /// Do not sort, show hidden files and disable color (implies -aU)
+    #[arg(short = 'f')]
+    unsorted_all: bool,
+
+    /// List one file per line
+    #[arg(short = '1')]
+    one_per_line: bool,
+
     /// Enable colorized output
     #[arg(short = 'c', long = "color", default_value = "true")]
     color: bool,
@@ -46,6 +195,95 @@ struct Args {
     /// Show SELinux security context
     #[arg(short = 'Z', long = "context")]
     selinux: bool,
+
+    /// Only show these SELinux context components, e.g. type or user,type
+    #[arg(
+        long = "context-fields",
+        value_name = "FIELDS",
+        value_enum,
+        value_delimiter = ',',
+        requires = "selinux"
+    )]
+    context_fields: Vec<ContextFieldArg>,
+
+    /// Shorten SELinux contexts wider than --max-context-width with "..."
+    #[arg(long = "truncate-context", requires = "selinux")]
+    truncate_context: bool,
+
+    /// Width SELinux contexts are truncated to [default: 32]
+    #[arg(
+        long = "max-context-width",
+        value_name = "WIDTH",
+        requires = "truncate_context"
+    )]
+    max_context_width: Option<usize>,
+
+    /// List POSIX ACL entries, with effective permissions, in long format
+    #[arg(long = "acl")]
+    acl: bool,
+
+    /// Show file capabilities in long format, as getcap prints them
+    #[arg(long = "caps")]
+    caps: bool,
+
+    /// Show permissions in octal (e.g. 4755) in long format
+    #[arg(long = "octal-perms")]
+    octal_perms: bool,
+
+    /// Explain who may read, write and execute each entry in long format
+    #[arg(long = "explain-perms")]
+    explain_perms: bool,
+
+    /// Show effective read, write and execute access in long format
+    #[arg(long = "access")]
+    access: bool,
+
+    /// Show inode flags (immutable, append-only, ...) in long format, as lsattr prints them
+    #[arg(long = "inode-flags")]
+    inode_flags: bool,
+
+    /// Report risky entries (setuid/setgid executables, world-writable files,
+    /// unowned files, ...) with a severity instead of listing; exits with 1
+    /// if anything is found
+    #[arg(long = "audit")]
+    audit: bool,
+
+    /// Audit subdirectories too
+    #[arg(long = "audit-recursive", requires = "audit")]
+    audit_recursive: bool,
+
+    /// Output format for audit findings
+    #[arg(
+        long = "audit-format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value = "text",
+        requires = "audit"
+    )]
+    audit_format: AuditFormatArg,
+
+    /// List extended attribute names and value sizes in long format
+    #[arg(short = '@', long = "xattr")]
+    xattr: bool,
+
+    /// Dump extended attribute values as hex or text
+    #[arg(
+        long = "xattr-dump",
+        value_name = "FORMAT",
+        value_enum,
+        requires = "xattr"
+    )]
+    xattr_dump: Option<XattrDumpArg>,
+
+    /// Only list extended attributes in these namespaces
+    #[arg(
+        long = "xattr-namespace",
+        value_name = "NS",
+        value_enum,
+        value_delimiter = ',',
+        requires = "xattr"
+    )]
+    xattr_namespaces: Vec<XattrNamespaceArg>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
@@ -53,38 +291,302 @@ enum SortBy {
     Name,
     Size,
     Time,
+    #[value(alias = "ext")]
     Extension,
     Type,
     Owner,
     Group,
+    Version,
+    Permissions,
+    Inode,
+    Blocks,
+    Links,
+    NameLength,
+    Birth,
+    Access,
     None,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum EntryType {
+    /// Regular file
+    F,
+    /// Directory
+    D,
+    /// Symbolic link
+    L,
+    /// Socket
+    S,
+    /// Named pipe
+    P,
+    /// Block device
+    B,
+    /// Character device
+    C,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum TimeFieldArg {
+    Mtime,
+    Atime,
+    Ctime,
+    Birth,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum GitIgnoreArg {
+    Hide,
+    Dim,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum ContextFieldArg {
+    User,
+    Role,
+    Type,
+    Range,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum AuditFormatArg {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum XattrDumpArg {
+    Hex,
+    Text,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum XattrNamespaceArg {
+    User,
+    Trusted,
+    Security,
+    System,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct SortSpec {
+    by: SortBy,
+    descending: bool,
+}
+
+/// Parse a sort key such as `size` or `time:desc`
+fn parse_sort_spec(spec: &str) -> Result<SortSpec, String> {
+    let (key, direction) = match spec.split_once(':') {
+        Some((key, direction)) => (key, Some(direction)),
+        None => (spec, None),
+    };
+
+    let by = SortBy::from_str(key, true)?;
+    let descending = match direction {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => return Err(format!("invalid sort direction '{}'", other)),
+    };
+
+    Ok(SortSpec { by, descending })
+}
+
+fn file_types(types: &[EntryType]) -> options::filtering::FileTypes {
+    if types.is_empty() {
+        return options::filtering::FileTypes::all();
+    }
+
+    options::filtering::FileTypes {
+        files: types.contains(&EntryType::F),
+        dirs: types.contains(&EntryType::D),
+        symlinks: types.contains(&EntryType::L),
+        sockets: types.contains(&EntryType::S),
+        pipes: types.contains(&EntryType::P),
+        block_devices: types.contains(&EntryType::B),
+        char_devices: types.contains(&EntryType::C),
+    }
+}
+
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.unsorted_all {
+        args.all = true;
+        args.unsorted = true;
+        args.color = false;
+    }
+    if args.unsorted {
+        args.sort = vec![SortSpec {
+            by: SortBy::None,
+            descending: false,
+        }];
+    }
+
+    // Whether SELinux is enabled comes from the kernel, not the flag
     let selinux_config = security::selinux::SELinuxConfig {
-        enabled: true,
-        show_context: args.selinux, // Use the selinux flag here
-        truncate_context: false,
-        max_context_width: None,
+        show_context: args.selinux,
+        fields: if args.context_fields.is_empty() {
+            security::selinux::ContextField::ALL.to_vec()
+        } else {
+            args.context_fields
+                .iter()
+                .map(|field| match field {
+                    ContextFieldArg::User => security::selinux::ContextField::User,
+                    ContextFieldArg::Role => security::selinux::ContextField::Role,
+                    ContextFieldArg::Type => security::selinux::ContextField::Type,
+                    ContextFieldArg::Range => security::selinux::ContextField::Range,
+                })
+                .collect()
+        },
+        truncate_context: args.truncate_context,
+        max_context_width: args
+            .truncate_context
+            .then(|| args.max_context_width.unwrap_or(32)),
+        ..Default::default()
+    };
+
+    let git_ignore = match (args.git_ignored, args.gitignore) {
+        (true, _) => options::gitignore::GitIgnoreMode::Only,
+        (false, Some(GitIgnoreArg::Hide)) => options::gitignore::GitIgnoreMode::Hide,
+        (false, Some(GitIgnoreArg::Dim)) => options::gitignore::GitIgnoreMode::Dim,
+        (false, None) => options::gitignore::GitIgnoreMode::Off,
     };
 
     let config = core::display::DisplayConfig {
         term_width: core::display::get_terminal_width(),
         color_enabled: args.color,
+        dim_ignored: git_ignore == options::gitignore::GitIgnoreMode::Dim,
+        show_git_status: args.git,
         use_long_format: args.long,
+        one_per_line: args.one_per_line,
         human_readable: args.human_readable,
         selinux_handler: if args.selinux {
-            // Only create handler if SELinux is enabled
+            // Only create handler if contexts are requested
             Some(security::selinux::SELinuxHandler::new(selinux_config))
         } else {
             None
         },
+        show_acl: args.acl,
+        show_capabilities: args.caps,
+        show_inode_flags: args.inode_flags,
+        show_octal_perms: args.octal_perms,
+        show_access: args.access,
+        explain_perms: args.explain_perms,
+        xattrs: args.xattr.then(|| security::xattr::XattrConfig {
+            namespaces: args
+                .xattr_namespaces
+                .iter()
+                .map(|ns| match ns {
+                    XattrNamespaceArg::User => security::xattr::XattrNamespace::User,
+                    XattrNamespaceArg::Trusted => security::xattr::XattrNamespace::Trusted,
+                    XattrNamespaceArg::Security => security::xattr::XattrNamespace::Security,
+                    XattrNamespaceArg::System => security::xattr::XattrNamespace::System,
+                })
+                .collect(),
+            dump: match args.xattr_dump {
+                Some(XattrDumpArg::Hex) => security::xattr::XattrDump::Hex,
+                Some(XattrDumpArg::Text) => security::xattr::XattrDump::Text,
+                None => security::xattr::XattrDump::None,
+            },
+        }),
+    };
+
+    let mut ignore_patterns = args.ignore.clone();
+    if args.ignore_backups {
+        ignore_patterns.extend(
+            ["*~", ".*~"]
+                .iter()
+                .filter_map(|pattern| options::filtering::parse_glob(pattern).ok()),
+        );
+    }
+
+    let time_field = match args.time_field {
+        TimeFieldArg::Mtime => options::filtering::TimeField::Modified,
+        TimeFieldArg::Atime => options::filtering::TimeField::Accessed,
+        TimeFieldArg::Ctime => options::filtering::TimeField::Changed,
+        TimeFieldArg::Birth => options::filtering::TimeField::Birth,
     };
 
+    // A reference file is compared using the same timestamp as the entries
+    let mut newer_than = args.newer;
+    if let Some(reference) = &args.newer_than {
+        match options::filtering::reference_time(reference, time_field) {
+            Ok(time) => newer_than = newer_than.max(Some(time)),
+            Err(e) => {
+                eprintln!("rust-ls: {}: {}", reference.display(), e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let filter_options = options::filtering::FilterOptions {
+        show_hidden: args.all || args.almost_all,
+        pattern: args.glob.clone().or_else(|| args.iglob.clone()),
+        ignore_patterns,
+        hide_patterns: args.hide.clone(),
+        regex: args.regex.clone().or_else(|| args.iregex.clone()),
+        min_size: args.min_size,
+        max_size: args.max_size,
+        newer_than,
+        older_than: args.older,
+        time_field,
+        uid: args.user,
+        gid: args.group,
+        uid_range: args.uid_range,
+        perm: args.perm,
+        git_ignore,
+        git_modified: args.git_modified,
+        git_untracked: args.git_untracked,
+        capabilities: args.has_caps,
+        can: args.can,
+        cannot: args.cannot,
+        expression: args.where_expr.clone(),
+        file_types: file_types(&args.types),
+    };
+
+    if args.audit {
+        std::process::exit(run_audit(&args));
+    }
+
     for path in &args.paths {
-        if let Err(e) = core::filesystem::list_directory(path, &args, &config) {
+        if let Err(e) = core::filesystem::list_directory(path, &args, &filter_options, &config) {
             eprintln!("rust-ls: {}: {}", path, e);
         }
     }
 }
+
+/// Audit the given paths and print the findings; returns the exit status
+fn run_audit(args: &Args) -> i32 {
+    let mut findings = Vec::new();
+    let mut failed = false;
+    for path in &args.paths {
+        match security::audit::audit_directory(Path::new(path), args.audit_recursive) {
+            Ok(report) => {
+                findings.extend(report.findings);
+                failed |= report.errors > 0;
+            }
+            Err(e) => {
+                eprintln!("rust-ls: {}: {}", path, e);
+                failed = true;
+            }
+        }
+    }
+
+    let format = match args.audit_format {
+        AuditFormatArg::Text => security::audit::AuditFormat::Text,
+        AuditFormatArg::Json => security::audit::AuditFormat::Json,
+    };
+    let stdout = std::io::stdout();
+    if let Err(e) =
+        security::audit::write_findings(&mut stdout.lock(), &mut findings, format, args.color)
+    {
+        eprintln!("rust-ls: {}", e);
+        return 2;
+    }
+
+    if failed {
+        2
+    } else if findings.is_empty() {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,571 @@
+use crate::core::filesystem::FileInfo;
+use crate::options::filtering::{
+    entry_time, parse_glob, parse_group, parse_perm, parse_size, parse_time, parse_user,
+    GlobPattern, PermMatch, TimeField,
+};
+use crate::security::capability;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// A parsed filter expression, e.g. `size > 10M and not hidden`
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Flag(Flag),
+    Compare(Field, CmpOp, Value),
+}
+
+/// Boolean properties that can be used on their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Hidden,
+    Dir,
+    File,
+    Symlink,
+    Executable,
+    Ignored,      // Ignored by git
+    Modified,     // Changed in the git index or work tree
+    Untracked,    // Not tracked by git
+    Capabilities, // Regular file with file capabilities
+    CanRead,      // The checked user may read it
+    CanWrite,     // The checked user may write it
+    CanExecute,   // The checked user may execute or search it
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Ext,
+    Path,
+    Type,
+    Size,
+    Blocks,
+    Links,
+    Inode,
+    Uid,
+    Gid,
+    Perm,
+    Time(TimeField),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,    // ==
+    Ne,    // !=
+    Lt,    // <
+    Le,    // <=
+    Gt,    // >
+    Ge,    // >=
+    Regex, // =~
+    Glob,  // ~
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(u64),
+    Text(String),
+    Regex(Regex),
+    Glob(GlobPattern),
+    Time(SystemTime),
+    Age(Duration), // Compared against the time elapsed since a timestamp
+    Perm(PermMatch),
+}
+
+/// Error from parsing an expression, with the 1-based column it refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Expr {
+    /// Whether the expression refers to the given flag anywhere
+    pub fn uses_flag(&self, flag: Flag) -> bool {
+        match self {
+            Expr::And(exprs) | Expr::Or(exprs) => exprs.iter().any(|expr| expr.uses_flag(flag)),
+            Expr::Not(expr) => expr.uses_flag(flag),
+            Expr::Flag(other) => *other == flag,
+            Expr::Compare(..) => false,
+        }
+    }
+
+    pub fn evaluate(&self, entry: &FileInfo) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.evaluate(entry)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.evaluate(entry)),
+            Expr::Not(expr) => !expr.evaluate(entry),
+            Expr::Flag(flag) => evaluate_flag(entry, *flag),
+            Expr::Compare(field, op, value) => evaluate_comparison(entry, *field, *op, value),
+        }
+    }
+}
+
+/// Parse a filter expression, for use as a clap value parser. The error
+/// message repeats the expression with a caret under the offending column.
+pub fn parse_filter(input: &str) -> Result<Expr, String> {
+    parse(input).map_err(|e| format!("{}\n  {}\n  {:>width$}", e, input, "^", width = e.column))
+}
+
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end_column: input.chars().count() + 1,
+    };
+
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(error(token.column, format!("unexpected '{}'", token.kind))),
+    }
+}
+
+fn error(column: usize, message: impl Into<String>) -> ParseError {
+    ParseError {
+        column,
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(CmpOp),
+    Word(String),
+    Str(String),
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::LParen => write!(f, "("),
+            TokenKind::RParen => write!(f, ")"),
+            TokenKind::And => write!(f, "and"),
+            TokenKind::Or => write!(f, "or"),
+            TokenKind::Not => write!(f, "not"),
+            TokenKind::Op(op) => write!(f, "{}", op),
+            TokenKind::Word(word) => write!(f, "{}", word),
+            TokenKind::Str(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+            CmpOp::Regex => "=~",
+            CmpOp::Glob => "~",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | ':' | '/' | '+' | '*' | '?')
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+        let (kind, len) = match (c, next) {
+            ('(', _) => (TokenKind::LParen, 1),
+            (')', _) => (TokenKind::RParen, 1),
+            ('=', Some('=')) => (TokenKind::Op(CmpOp::Eq), 2),
+            ('=', Some('~')) => (TokenKind::Op(CmpOp::Regex), 2),
+            ('!', Some('=')) => (TokenKind::Op(CmpOp::Ne), 2),
+            ('<', Some('=')) => (TokenKind::Op(CmpOp::Le), 2),
+            ('>', Some('=')) => (TokenKind::Op(CmpOp::Ge), 2),
+            ('<', _) => (TokenKind::Op(CmpOp::Lt), 1),
+            ('>', _) => (TokenKind::Op(CmpOp::Gt), 1),
+            ('~', _) => (TokenKind::Op(CmpOp::Glob), 1),
+            ('=', _) => (TokenKind::Op(CmpOp::Eq), 1),
+            ('&', Some('&')) => (TokenKind::And, 2),
+            ('|', Some('|')) => (TokenKind::Or, 2),
+            ('!', _) => (TokenKind::Not, 1),
+            ('"' | '\'', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&q| q == c)
+                    .ok_or_else(|| error(column, "unterminated string"))?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (TokenKind::Str(text), end + 2)
+            }
+            _ if is_word_char(c) => {
+                let len = chars[i..].iter().take_while(|&&c| is_word_char(c)).count();
+                let word: String = chars[i..i + len].iter().collect();
+                let kind = match word.to_lowercase().as_str() {
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                };
+                (kind, len)
+            }
+            _ => return Err(error(column, format!("unexpected character '{}'", c))),
+        };
+
+        tokens.push(Token { kind, column });
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end_column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| error(self.end_column, "unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn next_is(&self, kind: &TokenKind) -> bool {
+        self.peek().is_some_and(|token| token.kind == *kind)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.next_is(&TokenKind::Or) {
+            self.pos += 1;
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut exprs = vec![self.parse_unary()?];
+        while self.next_is(&TokenKind::And) {
+            self.pos += 1;
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.next_is(&TokenKind::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::LParen => {
+                let expr = self.parse_or()?;
+                let close = self.next()?;
+                if close.kind != TokenKind::RParen {
+                    return Err(error(
+                        close.column,
+                        format!("expected ')', found '{}'", close.kind),
+                    ));
+                }
+                Ok(expr)
+            }
+            TokenKind::Word(word) => self.parse_predicate(&word, token.column),
+            other => Err(error(
+                token.column,
+                format!("expected a field, found '{}'", other),
+            )),
+        }
+    }
+
+    fn parse_predicate(&mut self, name: &str, column: usize) -> Result<Expr, ParseError> {
+        if let Some(flag) = parse_flag(name) {
+            return Ok(Expr::Flag(flag));
+        }
+
+        let field =
+            parse_field(name).ok_or_else(|| error(column, format!("unknown field '{}'", name)))?;
+
+        let op_token = self.next()?;
+        let TokenKind::Op(op) = op_token.kind else {
+            return Err(error(
+                op_token.column,
+                format!(
+                    "expected a comparison after '{}', found '{}'",
+                    name, op_token.kind
+                ),
+            ));
+        };
+
+        let value_token = self.next()?;
+        let text = match value_token.kind {
+            TokenKind::Word(text) | TokenKind::Str(text) => text,
+            other => {
+                return Err(error(
+                    value_token.column,
+                    format!("expected a value after '{}', found '{}'", op, other),
+                ))
+            }
+        };
+
+        let (field, value) = parse_value(name, field, op, &text)
+            .map_err(|message| error(value_token.column, message))?;
+        Ok(Expr::Compare(field, op, value))
+    }
+}
+
+fn parse_flag(name: &str) -> Option<Flag> {
+    match name.to_lowercase().as_str() {
+        "hidden" => Some(Flag::Hidden),
+        "dir" => Some(Flag::Dir),
+        "file" => Some(Flag::File),
+        "symlink" | "link" => Some(Flag::Symlink),
+        "executable" | "exec" => Some(Flag::Executable),
+        "ignored" => Some(Flag::Ignored),
+        "modified" => Some(Flag::Modified),
+        "untracked" => Some(Flag::Untracked),
+        "caps" | "capabilities" => Some(Flag::Capabilities),
+        "can_read" => Some(Flag::CanRead),
+        "can_write" => Some(Flag::CanWrite),
+        "can_exec" | "can_execute" => Some(Flag::CanExecute),
+        _ => None,
+    }
+}
+
+/// Fields by name; `user` and `group` are compared as UID and GID
+fn parse_field(name: &str) -> Option<Field> {
+    match name.to_lowercase().as_str() {
+        "name" => Some(Field::Name),
+        "ext" | "extension" => Some(Field::Ext),
+        "path" => Some(Field::Path),
+        "type" => Some(Field::Type),
+        "size" => Some(Field::Size),
+        "blocks" => Some(Field::Blocks),
+        "links" => Some(Field::Links),
+        "inode" => Some(Field::Inode),
+        "uid" | "user" => Some(Field::Uid),
+        "gid" | "group" => Some(Field::Gid),
+        "perm" => Some(Field::Perm),
+        "mtime" => Some(Field::Time(TimeField::Modified)),
+        "atime" => Some(Field::Time(TimeField::Accessed)),
+        "ctime" => Some(Field::Time(TimeField::Changed)),
+        "btime" | "birth" => Some(Field::Time(TimeField::Birth)),
+        _ => None,
+    }
+}
+
+/// Resolve the literal on the right-hand side of a comparison into a typed
+/// value for the given field, rejecting operators that don't apply to it
+fn parse_value(name: &str, field: Field, op: CmpOp, text: &str) -> Result<(Field, Value), String> {
+    let unsupported = || format!("operator '{}' does not apply to '{}'", op, name);
+
+    let value = match field {
+        Field::Name | Field::Ext | Field::Path | Field::Type => match op {
+            CmpOp::Regex => Value::Regex(Regex::new(text).map_err(|e| e.to_string())?),
+            CmpOp::Glob => Value::Glob(parse_glob(text)?),
+            _ if field == Field::Type => {
+                if !matches!(text, "f" | "d" | "l" | "s" | "p" | "b" | "c") {
+                    return Err(format!(
+                        "invalid type '{}' (expected f, d, l, s, p, b or c)",
+                        text
+                    ));
+                }
+                Value::Text(text.to_string())
+            }
+            _ => Value::Text(text.to_string()),
+        },
+        Field::Uid | Field::Gid if matches!(op, CmpOp::Regex | CmpOp::Glob) => {
+            return Err(unsupported())
+        }
+        Field::Uid => Value::Number(u64::from(parse_user(text)?)),
+        Field::Gid => Value::Number(u64::from(parse_group(text)?)),
+        Field::Size | Field::Blocks | Field::Links | Field::Inode => {
+            if matches!(op, CmpOp::Regex | CmpOp::Glob) {
+                return Err(unsupported());
+            }
+            Value::Number(parse_size(text)?)
+        }
+        Field::Perm => {
+            if !matches!(op, CmpOp::Eq | CmpOp::Ne) {
+                return Err(unsupported());
+            }
+            Value::Perm(parse_perm(text)?)
+        }
+        Field::Time(_) => {
+            if matches!(op, CmpOp::Regex | CmpOp::Glob) {
+                return Err(unsupported());
+            }
+            // A bare duration compares the entry's age; anything else is a
+            // point in time
+            match crate::options::filtering::parse_duration(text) {
+                Some(age) => Value::Age(age),
+                None => Value::Time(parse_time(text)?),
+            }
+        }
+    };
+
+    Ok((field, value))
+}
+
+fn evaluate_flag(entry: &FileInfo, flag: Flag) -> bool {
+    let metadata = &entry.metadata;
+    match flag {
+        Flag::Hidden => entry.name.starts_with('.'),
+        Flag::Dir => metadata.is_dir(),
+        Flag::File => metadata.is_file(),
+        Flag::Symlink => metadata.file_type().is_symlink(),
+        Flag::Executable => metadata.is_file() && metadata.mode() & 0o111 != 0,
+        Flag::Ignored => entry.git_ignored,
+        Flag::Modified => entry.git_status.is_some_and(|status| status.is_modified()),
+        Flag::Untracked => entry.git_status.is_some_and(|status| status.is_untracked()),
+        Flag::Capabilities => capability::has_capabilities(Path::new(&entry.path), metadata),
+        Flag::CanRead => entry.access.is_some_and(|access| access.read),
+        Flag::CanWrite => entry.access.is_some_and(|access| access.write),
+        Flag::CanExecute => entry.access.is_some_and(|access| access.execute),
+    }
+}
+
+fn type_char(entry: &FileInfo) -> &'static str {
+    let file_type = entry.metadata.file_type();
+    if file_type.is_dir() {
+        "d"
+    } else if file_type.is_symlink() {
+        "l"
+    } else if file_type.is_socket() {
+        "s"
+    } else if file_type.is_fifo() {
+        "p"
+    } else if file_type.is_block_device() {
+        "b"
+    } else if file_type.is_char_device() {
+        "c"
+    } else {
+        "f"
+    }
+}
+
+fn order_matches(ordering: Ordering, op: CmpOp) -> bool {
+    match op {
+        CmpOp::Eq => ordering == Ordering::Equal,
+        CmpOp::Ne => ordering != Ordering::Equal,
+        CmpOp::Lt => ordering == Ordering::Less,
+        CmpOp::Le => ordering != Ordering::Greater,
+        CmpOp::Gt => ordering == Ordering::Greater,
+        CmpOp::Ge => ordering != Ordering::Less,
+        CmpOp::Regex | CmpOp::Glob => false,
+    }
+}
+
+fn evaluate_comparison(entry: &FileInfo, field: Field, op: CmpOp, value: &Value) -> bool {
+    let metadata = &entry.metadata;
+    match field {
+        Field::Name | Field::Ext | Field::Path | Field::Type => {
+            let text = match field {
+                Field::Name => entry.name.as_str(),
+                Field::Ext => Path::new(&entry.name)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or(""),
+                Field::Path => entry.path.as_str(),
+                _ => type_char(entry),
+            };
+            match (op, value) {
+                (CmpOp::Regex, Value::Regex(regex)) => regex.is_match(text),
+                (CmpOp::Glob, Value::Glob(pattern)) => pattern.matches(text),
+                (_, Value::Text(other)) => order_matches(text.cmp(other.as_str()), op),
+                _ => false,
+            }
+        }
+        Field::Size | Field::Blocks | Field::Links | Field::Inode | Field::Uid | Field::Gid => {
+            let number = match field {
+                Field::Size => metadata.len(),
+                Field::Blocks => metadata.blocks(),
+                Field::Links => metadata.nlink(),
+                Field::Inode => metadata.ino(),
+                Field::Uid => u64::from(metadata.uid()),
+                _ => u64::from(metadata.gid()),
+            };
+            match value {
+                Value::Number(other) => order_matches(number.cmp(other), op),
+                _ => false,
+            }
+        }
+        Field::Perm => match value {
+            Value::Perm(perm) => perm.matches(metadata.mode()) == (op == CmpOp::Eq),
+            _ => false,
+        },
+        Field::Time(time_field) => {
+            let Some(time) = entry_time(metadata, time_field) else {
+                return false;
+            };
+            match value {
+                Value::Time(other) => order_matches(time.cmp(other), op),
+                Value::Age(max_age) => {
+                    // Timestamps in the future count as age zero
+                    let age = SystemTime::now()
+                        .duration_since(time)
+                        .unwrap_or(Duration::ZERO);
+                    order_matches(age.cmp(max_age), op)
+                }
+                _ => false,
+            }
+        }
+    }
+}
@@ -1,16 +1,68 @@
 use crate::core::filesystem::FileInfo;
-use regex::Regex;
-use std::os::unix::fs::FileTypeExt;
+use crate::options::expression::{CmpOp, Expr, Field, Flag, Value};
+use crate::options::gitignore::GitIgnoreMode;
+use crate::security::access::Access;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use regex::{Regex, RegexBuilder};
+use std::fs::{self, Metadata};
+use std::io;
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Default)]
 pub struct FilterOptions {
     pub show_hidden: bool,
-    pub pattern: Option<String>,
+    pub pattern: Option<GlobPattern>,
+    pub ignore_patterns: Vec<GlobPattern>, // Never list entries matching these (-I, -B)
+    pub hide_patterns: Vec<GlobPattern>,   // Hide matching entries unless -a or -A is given
     pub regex: Option<Regex>,
     pub min_size: Option<u64>,
     pub max_size: Option<u64>,
+    pub newer_than: Option<SystemTime>, // Only entries whose time_field is after this
+    pub older_than: Option<SystemTime>, // Only entries whose time_field is before this
+    pub time_field: TimeField,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub uid_range: Option<(u32, u32)>, // Inclusive bounds
+    pub perm: Option<PermMatch>,
     pub file_types: FileTypes,
+    pub git_ignore: GitIgnoreMode,
+    pub git_modified: bool,  // Only entries changed in the index or work tree
+    pub git_untracked: bool, // Only untracked entries
+    pub capabilities: bool,  // Only files with file capabilities
+    pub can: Option<Access>, // Only entries the checked user has all of these rights on
+    pub cannot: Option<Access>, // Only entries the checked user has none of these rights on
+    pub expression: Option<Expr>, // Parsed --where expression
+}
+
+/// Permission predicate with find's `-perm` semantics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermMatch {
+    Exact(u32), // MODE: permission bits are exactly MODE
+    All(u32),   // -MODE: all of the bits in MODE are set
+    Any(u32),   // /MODE: any of the bits in MODE are set
+}
+
+impl PermMatch {
+    pub fn matches(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match *self {
+            PermMatch::Exact(bits) => mode == bits,
+            PermMatch::All(bits) => mode & bits == bits,
+            // find treats "/000" as matching everything
+            PermMatch::Any(bits) => bits == 0 || mode & bits != 0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeField {
+    #[default]
+    Modified, // mtime
+    Accessed, // atime
+    Changed,  // ctime (inode change)
+    Birth,    // creation time, where the filesystem records it
 }
 
 #[derive(Debug, Default)]
@@ -20,7 +72,8 @@ pub struct FileTypes {
     pub symlinks: bool,
     pub sockets: bool,
     pub pipes: bool,
-    pub devices: bool,
+    pub block_devices: bool,
+    pub char_devices: bool,
 }
 
 impl FileTypes {
@@ -31,99 +84,550 @@ impl FileTypes {
             symlinks: true,
             sockets: true,
             pipes: true,
-            devices: true,
+            block_devices: true,
+            char_devices: true,
         }
     }
 }
 
 pub fn filter_entries(entries: Vec<FileInfo>, options: &FilterOptions) -> Vec<FileInfo> {
+    let filter = options.to_expr();
     entries
         .into_iter()
-        .filter(|entry| should_include_entry(entry, options))
+        .filter(|entry| filter.evaluate(entry))
         .collect()
 }
 
-fn should_include_entry(entry: &FileInfo, options: &FilterOptions) -> bool {
-    // Check hidden files
-    if !options.show_hidden && entry.name.starts_with('.') {
-        return false;
+impl FilterOptions {
+    /// Whether entries need their git index and work tree status
+    pub fn needs_git_status(&self) -> bool {
+        self.git_modified
+            || self.git_untracked
+            || self.expression.as_ref().is_some_and(|expr| {
+                expr.uses_flag(Flag::Modified) || expr.uses_flag(Flag::Untracked)
+            })
     }
 
-    // Check file type
-    if !matches_file_type(entry, &options.file_types) {
-        return false;
+    /// Whether entries need the checked user's effective access
+    pub fn needs_access(&self) -> bool {
+        self.can.is_some()
+            || self.cannot.is_some()
+            || self.expression.as_ref().is_some_and(|expr| {
+                expr.uses_flag(Flag::CanRead)
+                    || expr.uses_flag(Flag::CanWrite)
+                    || expr.uses_flag(Flag::CanExecute)
+            })
     }
 
-    // Check size constraints
-    if let Some(min_size) = options.min_size {
-        if entry.metadata.len() < min_size {
-            return false;
-        }
+    /// Whether an entry is left out by its name alone (hidden files, `--hide`,
+    /// `-I` and `-B`), so it can be skipped before it is stat'ed
+    pub fn excludes_name(&self, name: &str) -> bool {
+        let hidden = !self.show_hidden
+            && (name.starts_with('.') || self.hide_patterns.iter().any(|p| p.matches(name)));
+        hidden || self.ignore_patterns.iter().any(|p| p.matches(name))
     }
-    if let Some(max_size) = options.max_size {
-        if entry.metadata.len() > max_size {
-            return false;
-        }
+
+    /// Whether entries need their git ignore status
+    pub fn needs_gitignore(&self) -> bool {
+        self.git_ignore != GitIgnoreMode::Off
+            || self
+                .expression
+                .as_ref()
+                .is_some_and(|expr| expr.uses_flag(Flag::Ignored))
     }
 
-    // Check pattern matching
-    if let Some(pattern) = &options.pattern {
-        if !Path::new(&entry.name).matches_pattern(pattern) {
-            return false;
+    /// Lower the fixed filter options, and any `--where` expression, into a
+    /// single expression so every entry goes through one evaluation path
+    pub fn to_expr(&self) -> Expr {
+        let name_matches = |pattern: &GlobPattern| {
+            Expr::Compare(Field::Name, CmpOp::Glob, Value::Glob(pattern.clone()))
+        };
+        let not = |expr: Expr| Expr::Not(Box::new(expr));
+        let mut exprs = Vec::new();
+
+        // Hidden files and --hide patterns
+        if !self.show_hidden {
+            exprs.push(not(Expr::Flag(Flag::Hidden)));
+            exprs.extend(self.hide_patterns.iter().map(name_matches).map(not));
+        }
+
+        // Ignore patterns (-I, -B)
+        exprs.extend(self.ignore_patterns.iter().map(name_matches).map(not));
+
+        // File type
+        if let Some(types) = self.file_types.to_expr() {
+            exprs.push(types);
+        }
+
+        // Size constraints
+        if let Some(min_size) = self.min_size {
+            exprs.push(Expr::Compare(
+                Field::Size,
+                CmpOp::Ge,
+                Value::Number(min_size),
+            ));
+        }
+        if let Some(max_size) = self.max_size {
+            exprs.push(Expr::Compare(
+                Field::Size,
+                CmpOp::Le,
+                Value::Number(max_size),
+            ));
+        }
+
+        // Time range
+        let time = Field::Time(self.time_field);
+        if let Some(newer) = self.newer_than {
+            exprs.push(Expr::Compare(time, CmpOp::Gt, Value::Time(newer)));
+        }
+        if let Some(older) = self.older_than {
+            exprs.push(Expr::Compare(time, CmpOp::Lt, Value::Time(older)));
+        }
+
+        // Ownership and permission bits
+        if let Some(uid) = self.uid {
+            exprs.push(Expr::Compare(
+                Field::Uid,
+                CmpOp::Eq,
+                Value::Number(u64::from(uid)),
+            ));
+        }
+        if let Some(gid) = self.gid {
+            exprs.push(Expr::Compare(
+                Field::Gid,
+                CmpOp::Eq,
+                Value::Number(u64::from(gid)),
+            ));
+        }
+        if let Some((min, max)) = self.uid_range {
+            exprs.push(Expr::Compare(
+                Field::Uid,
+                CmpOp::Ge,
+                Value::Number(u64::from(min)),
+            ));
+            exprs.push(Expr::Compare(
+                Field::Uid,
+                CmpOp::Le,
+                Value::Number(u64::from(max)),
+            ));
+        }
+        if let Some(perm) = self.perm {
+            exprs.push(Expr::Compare(Field::Perm, CmpOp::Eq, Value::Perm(perm)));
+        }
+
+        // Pattern and regex matching
+        if let Some(pattern) = &self.pattern {
+            exprs.push(name_matches(pattern));
+        }
+        if let Some(regex) = &self.regex {
+            exprs.push(Expr::Compare(
+                Field::Name,
+                CmpOp::Regex,
+                Value::Regex(regex.clone()),
+            ));
+        }
+
+        // Entries ignored by git
+        match self.git_ignore {
+            GitIgnoreMode::Hide => exprs.push(not(Expr::Flag(Flag::Ignored))),
+            GitIgnoreMode::Only => exprs.push(Expr::Flag(Flag::Ignored)),
+            GitIgnoreMode::Off | GitIgnoreMode::Dim => {}
+        }
+
+        // Entries changed or untracked in git; either one qualifies
+        let mut git_states = Vec::new();
+        if self.git_modified {
+            git_states.push(Expr::Flag(Flag::Modified));
+        }
+        if self.git_untracked {
+            git_states.push(Expr::Flag(Flag::Untracked));
+        }
+        if !git_states.is_empty() {
+            exprs.push(Expr::Or(git_states));
+        }
+
+        if self.capabilities {
+            exprs.push(Expr::Flag(Flag::Capabilities));
         }
+
+        // Effective access of the checked user
+        if let Some(can) = &self.can {
+            exprs.extend(access_flags(can).into_iter().map(Expr::Flag));
+        }
+        if let Some(cannot) = &self.cannot {
+            exprs.extend(access_flags(cannot).into_iter().map(Expr::Flag).map(not));
+        }
+
+        // Free-form --where expression
+        if let Some(expression) = &self.expression {
+            exprs.push(expression.clone());
+        }
+
+        Expr::And(exprs)
     }
+}
 
-    // Check regex matching
-    if let Some(regex) = &options.regex {
-        if !regex.is_match(&entry.name) {
-            return false;
+/// Flags for each right set in `access`
+fn access_flags(access: &Access) -> Vec<Flag> {
+    [
+        (access.read, Flag::CanRead),
+        (access.write, Flag::CanWrite),
+        (access.execute, Flag::CanExecute),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, flag)| *flag)
+    .collect()
+}
+
+impl FileTypes {
+    /// Expression matching the selected types, or `None` if all are selected
+    fn to_expr(&self) -> Option<Expr> {
+        let selected = [
+            (self.files, "f"),
+            (self.dirs, "d"),
+            (self.symlinks, "l"),
+            (self.sockets, "s"),
+            (self.pipes, "p"),
+            (self.block_devices, "b"),
+            (self.char_devices, "c"),
+        ];
+        if selected.iter().all(|(enabled, _)| *enabled) {
+            return None;
         }
+
+        Some(Expr::Or(
+            selected
+                .iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, kind)| {
+                    Expr::Compare(Field::Type, CmpOp::Eq, Value::Text(kind.to_string()))
+                })
+                .collect(),
+        ))
     }
+}
 
-    true
+/// A compiled shell glob, with brace alternatives (`*.{rs,toml}`) expanded
+#[derive(Debug, Clone)]
+pub struct GlobPattern {
+    patterns: Vec<glob::Pattern>,
+    case_sensitive: bool,
 }
 
-fn matches_file_type(entry: &FileInfo, types: &FileTypes) -> bool {
-    if entry.metadata.is_file() && types.files {
-        return true;
+impl GlobPattern {
+    pub fn new(pattern: &str, case_sensitive: bool) -> Result<Self, String> {
+        let patterns = expand_braces(pattern)
+            .iter()
+            .map(|expanded| glob::Pattern::new(expanded))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid glob '{}': {}", pattern, e))?;
+
+        Ok(Self {
+            patterns,
+            case_sensitive,
+        })
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        let options = glob::MatchOptions {
+            case_sensitive: self.case_sensitive,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_with(text, options))
     }
-    if entry.metadata.is_dir() && types.dirs {
-        return true;
+}
+
+/// Parse a case-sensitive glob pattern
+pub fn parse_glob(pattern: &str) -> Result<GlobPattern, String> {
+    GlobPattern::new(pattern, true)
+}
+
+/// Parse a case-insensitive glob pattern
+pub fn parse_iglob(pattern: &str) -> Result<GlobPattern, String> {
+    GlobPattern::new(pattern, false)
+}
+
+/// Parse a case-insensitive regular expression
+pub fn parse_iregex(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Expand brace alternatives, e.g. `*.{rs,toml}` into `*.rs` and `*.toml`.
+/// Groups may nest; a group without a comma or closing brace is literal.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut search_from = 0;
+
+    while let Some(offset) = chars[search_from..].iter().position(|&c| c == '{') {
+        let open = search_from + offset;
+        let mut depth = 0;
+        let mut alternatives = Vec::new();
+        let mut start = open + 1;
+        let mut close = None;
+
+        for (i, &c) in chars.iter().enumerate().skip(open) {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        alternatives.push(&chars[start..i]);
+                        close = Some(i);
+                        break;
+                    }
+                }
+                ',' if depth == 1 => {
+                    alternatives.push(&chars[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+
+        match close {
+            Some(close) if alternatives.len() > 1 => {
+                let prefix: String = chars[..open].iter().collect();
+                let suffix: String = chars[close + 1..].iter().collect();
+                return alternatives
+                    .iter()
+                    .flat_map(|alternative| {
+                        let alternative: String = alternative.iter().collect();
+                        expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+                    })
+                    .collect();
+            }
+            _ => search_from = open + 1,
+        }
     }
-    if entry.metadata.file_type().is_symlink() && types.symlinks {
-        return true;
+
+    vec![pattern.to_string()]
+}
+
+/// Parse a size such as `512`, `10K` or `2G` (binary multiples) into bytes
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, suffix) = input.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", input))?;
+    let multiplier: u64 = match suffix {
+        "" | "B" => 1,
+        "K" | "k" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        "P" => 1 << 50,
+        _ => return Err(format!("invalid size suffix '{}'", suffix)),
+    };
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{}' is too large", input))
+}
+
+/// Get the selected timestamp of an entry, if the platform provides it
+pub fn entry_time(metadata: &Metadata, field: TimeField) -> Option<SystemTime> {
+    match field {
+        TimeField::Modified => metadata.modified().ok(),
+        TimeField::Accessed => metadata.accessed().ok(),
+        TimeField::Changed => stat_time(metadata.ctime(), metadata.ctime_nsec()),
+        TimeField::Birth => metadata.created().ok(),
     }
-    if entry.metadata.file_type().is_socket() && types.sockets {
-        return true;
+}
+
+/// Convert a raw `stat` timestamp, which may lie before the epoch
+fn stat_time(secs: i64, nsecs: i64) -> Option<SystemTime> {
+    let seconds = Duration::from_secs(secs.unsigned_abs());
+    let time = if secs < 0 {
+        UNIX_EPOCH.checked_sub(seconds)?
+    } else {
+        UNIX_EPOCH.checked_add(seconds)?
+    };
+    time.checked_add(Duration::from_nanos(u64::try_from(nsecs).ok()?))
+}
+
+/// Get the selected timestamp of a reference file, as with find's `-newer`
+pub fn reference_time(path: &Path, field: TimeField) -> io::Result<SystemTime> {
+    let metadata = fs::metadata(path)?;
+    entry_time(&metadata, field)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "timestamp not available"))
+}
+
+/// Parse a point in time, either relative to now (`30m`, `2h`, `7d`) or
+/// absolute in local time (`2024-01-31`, `2024-01-31 12:00`, RFC 3339)
+pub fn parse_time(input: &str) -> Result<SystemTime, String> {
+    let input = input.trim();
+
+    if let Some(age) = parse_duration(input) {
+        return SystemTime::now()
+            .checked_sub(age)
+            .ok_or_else(|| format!("time '{}' is out of range", input));
     }
-    if entry.metadata.file_type().is_fifo() && types.pipes {
-        return true;
+
+    parse_date(input).ok_or_else(|| {
+        format!(
+            "invalid time '{}' (expected e.g. 2h, 7d, 2024-01-31 or 2024-01-31 12:00)",
+            input
+        )
+    })
+}
+
+/// Parse a duration such as `45s`, `30m`, `2h`, `7d` or `2w`
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let split = input.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = input.split_at(split);
+
+    let number: u64 = number.parse().ok()?;
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    number.checked_mul(seconds).map(Duration::from_secs)
+}
+
+fn parse_date(input: &str) -> Option<SystemTime> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Some(datetime.into());
     }
-    if (entry.metadata.file_type().is_block_device() || entry.metadata.file_type().is_char_device())
-        && types.devices
-    {
-        return true;
+
+    let naive = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(SystemTime::from)
+}
+
+/// Parse a user name or numeric UID
+pub fn parse_user(input: &str) -> Result<u32, String> {
+    input
+        .parse()
+        .ok()
+        .or_else(|| users::get_user_by_name(input).map(|user| user.uid()))
+        .ok_or_else(|| format!("unknown user '{}'", input))
+}
+
+/// Parse a group name or numeric GID
+pub fn parse_group(input: &str) -> Result<u32, String> {
+    input
+        .parse()
+        .ok()
+        .or_else(|| users::get_group_by_name(input).map(|group| group.gid()))
+        .ok_or_else(|| format!("unknown group '{}'", input))
+}
+
+/// Parse an inclusive UID range such as `1000-60000`, `1000-` or `-999`
+pub fn parse_uid_range(input: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("invalid UID range '{}' (expected MIN-MAX)", input);
+    let (min, max) = input.split_once('-').ok_or_else(invalid)?;
+
+    let min = if min.is_empty() {
+        0
+    } else {
+        min.parse().map_err(|_| invalid())?
+    };
+    let max = if max.is_empty() {
+        u32::MAX
+    } else {
+        max.parse().map_err(|_| invalid())?
+    };
+
+    if min > max {
+        return Err(invalid());
     }
-    false
+    Ok((min, max))
 }
 
-trait PatternMatching {
-    fn matches_pattern(&self, pattern: &str) -> bool;
+/// Parse a find-style permission predicate: `MODE`, `-MODE` or `/MODE`,
+/// where MODE is octal (`4000`) or symbolic (`u+s`, `g=w,o=w`)
+pub fn parse_perm(input: &str) -> Result<PermMatch, String> {
+    let (constructor, mode): (fn(u32) -> PermMatch, &str) =
+        if let Some(mode) = input.strip_prefix('-') {
+            (PermMatch::All, mode)
+        } else if let Some(mode) = input.strip_prefix('/') {
+            (PermMatch::Any, mode)
+        } else {
+            (PermMatch::Exact, input)
+        };
+
+    let bits = if mode.chars().all(|c| c.is_digit(8)) && !mode.is_empty() {
+        u32::from_str_radix(mode, 8)
+            .ok()
+            .filter(|bits| *bits <= 0o7777)
+    } else {
+        parse_symbolic_mode(mode)
+    };
+
+    bits.map(constructor)
+        .ok_or_else(|| format!("invalid mode '{}'", input))
 }
 
-impl PatternMatching for Path {
-    fn matches_pattern(&self, pattern: &str) -> bool {
-        if let Some(name) = self.file_name() {
-            if let Some(name_str) = name.to_str() {
-                return pattern_match(name_str, pattern);
-            }
+/// Parse symbolic mode clauses like `u+rwx,go=r` into permission bits,
+/// starting from an empty mode as find does
+fn parse_symbolic_mode(mode: &str) -> Option<u32> {
+    let mut bits = 0;
+
+    for clause in mode.split(',') {
+        let op_index = clause.find(['+', '='])?;
+        let (who, perms) = clause.split_at(op_index);
+
+        let mut who_mask = 0;
+        for c in who.chars() {
+            who_mask |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return None,
+            };
+        }
+        if who_mask == 0 {
+            who_mask = 0o7777;
+        }
+
+        let mut perm_bits = 0;
+        for c in perms[1..].chars() {
+            perm_bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => return None,
+            };
         }
-        false
+
+        // Both '+' and '=' just add bits when starting from an empty mode
+        bits |= who_mask & perm_bits;
     }
-}
 
-fn pattern_match(name: &str, pattern: &str) -> bool {
-    glob::Pattern::new(pattern)
-        .map(|p| p.matches(name))
-        .unwrap_or(false)
+    Some(bits)
 }
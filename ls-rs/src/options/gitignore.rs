@@ -0,0 +1,104 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How entries ignored by git are treated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GitIgnoreMode {
+    #[default]
+    Off,
+    Hide, // Leave ignored entries out
+    Dim,  // List ignored entries dimmed
+    Only, // List only ignored entries
+}
+
+/// The ignore rules that apply to the entries of one directory
+pub struct GitIgnore {
+    // In order of precedence: .gitignore files from the listed directory up
+    // to the repository root, then .git/info/exclude, then global excludes
+    matchers: Vec<Gitignore>,
+    dir: PathBuf,
+    dir_ignored: bool,
+}
+
+impl GitIgnore {
+    /// Load the rules for a directory, or `None` if it is not inside a git
+    /// work tree
+    pub fn for_directory(dir: &Path) -> Option<Self> {
+        let dir = fs::canonicalize(dir).ok()?;
+        let root = dir
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())?;
+
+        let mut matchers: Vec<Gitignore> = dir
+            .ancestors()
+            .take_while(|ancestor| ancestor.starts_with(root))
+            .map(|ancestor| Gitignore::new(ancestor.join(".gitignore")).0)
+            .collect();
+
+        if let Some(git_dir) = git_dir(root) {
+            let mut builder = GitignoreBuilder::new(root);
+            builder.add(git_dir.join("info").join("exclude"));
+            if let Ok(exclude) = builder.build() {
+                matchers.push(exclude);
+            }
+        }
+        matchers.push(GitignoreBuilder::new(root).build_global().0);
+
+        let mut gitignore = Self {
+            matchers,
+            dir: dir.clone(),
+            dir_ignored: false,
+        };
+
+        // Git never looks inside an excluded directory, so everything below
+        // one is ignored too
+        gitignore.dir_ignored = dir
+            .ancestors()
+            .take_while(|ancestor| *ancestor != root)
+            .any(|ancestor| gitignore.matched(ancestor, true));
+
+        Some(gitignore)
+    }
+
+    /// Whether the entry `name` of the loaded directory is ignored
+    pub fn is_ignored(&self, name: &str, is_dir: bool) -> bool {
+        if name == "." || name == ".." || name == ".git" {
+            return false;
+        }
+        self.dir_ignored || self.matched(&self.dir.join(name), is_dir)
+    }
+
+    fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in &self.matchers {
+            if !path.starts_with(matcher.path()) {
+                continue;
+            }
+            match matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Locate the git directory of a work tree, following the `gitdir:` file
+/// used by linked worktrees and submodules
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let git_dir = root.join(contents.strip_prefix("gitdir:")?.trim());
+
+    // Linked worktrees share info/exclude with the main repository
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => Some(git_dir.join(common.trim())),
+        Err(_) => Some(git_dir),
+    }
+}
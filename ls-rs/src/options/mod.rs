@@ -1,2 +1,4 @@
+pub mod expression;
 pub mod filtering;
 pub mod formatting;
+pub mod gitignore;
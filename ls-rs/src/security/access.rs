@@ -0,0 +1,204 @@
+use crate::security::acl::{self, AclTag};
+use crate::security::inode_flags;
+use std::ffi::CString;
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+/// Whether a user may read, write and execute (or search) an entry
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Access {
+    fn from_bits(bits: u32) -> Self {
+        Self {
+            read: bits & 0o4 != 0,
+            write: bits & 0o2 != 0,
+            execute: bits & 0o1 != 0,
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    /// `rwx` notation, e.g. `r-x`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' },
+            if self.execute { 'x' } else { '-' }
+        )
+    }
+}
+
+/// Parse a set of access letters such as `r` or `rw`
+pub fn parse_access(input: &str) -> Result<Access, String> {
+    let mut access = Access::default();
+    for c in input.chars() {
+        match c {
+            'r' => access.read = true,
+            'w' => access.write = true,
+            'x' => access.execute = true,
+            _ => return Err(format!("invalid access letter '{}', expected r, w or x", c)),
+        }
+    }
+    Ok(access)
+}
+
+/// Works out what a user may do with entries
+pub enum AccessChecker {
+    /// The invoking user, asked from the kernel with faccessat(AT_EACCESS),
+    /// which accounts for every access control in place
+    Current,
+    /// Another user, evaluated from the mode bits, supplementary groups,
+    /// POSIX ACLs, read-only mounts and inode flags
+    User {
+        uid: u32,
+        gid: u32,
+        groups: Vec<u32>,
+    },
+}
+
+impl AccessChecker {
+    pub fn new(as_user: Option<u32>) -> Self {
+        let Some(uid) = as_user else {
+            return AccessChecker::Current;
+        };
+
+        match users::get_user_by_uid(uid) {
+            Some(user) => {
+                let groups = users::get_user_groups(user.name(), user.primary_group_id())
+                    .map(|groups| groups.iter().map(|group| group.gid()).collect())
+                    .unwrap_or_else(|| vec![user.primary_group_id()]);
+                AccessChecker::User {
+                    uid,
+                    gid: user.primary_group_id(),
+                    groups,
+                }
+            }
+            // A UID without a passwd entry still has the access of others
+            None => AccessChecker::User {
+                uid,
+                gid: u32::MAX,
+                groups: Vec::new(),
+            },
+        }
+    }
+
+    /// Access to an entry; symlinks are resolved, as for open()
+    pub fn check(&self, path: &Path, metadata: &Metadata) -> Access {
+        match self {
+            AccessChecker::Current => Access {
+                read: kernel_access(path, libc::R_OK),
+                write: kernel_access(path, libc::W_OK),
+                execute: kernel_access(path, libc::X_OK),
+            },
+            AccessChecker::User { uid, gid, groups } => {
+                if !metadata.file_type().is_symlink() {
+                    return evaluate(path, metadata, *uid, *gid, groups);
+                }
+
+                // Judge a symlink by its target; a dangling one grants nothing
+                match fs::canonicalize(path).and_then(|target| {
+                    let metadata = fs::metadata(&target)?;
+                    Ok((target, metadata))
+                }) {
+                    Ok((target, metadata)) => evaluate(&target, &metadata, *uid, *gid, groups),
+                    Err(_) => Access::default(),
+                }
+            }
+        }
+    }
+}
+
+fn kernel_access(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::faccessat(libc::AT_FDCWD, path.as_ptr(), mode, libc::AT_EACCESS) == 0 }
+}
+
+/// The kernel's permission check for a user other than ourselves
+fn evaluate(path: &Path, metadata: &Metadata, uid: u32, gid: u32, groups: &[u32]) -> Access {
+    let mode = metadata.permissions().mode();
+    let in_group = |id: u32| id == gid || groups.contains(&id);
+
+    let mut access = if uid == 0 {
+        // Root bypasses the permission bits, but only executes files that
+        // someone may execute
+        Access {
+            read: true,
+            write: true,
+            execute: metadata.is_dir() || mode & 0o111 != 0,
+        }
+    } else if uid == metadata.uid() {
+        Access::from_bits(mode >> 6)
+    } else {
+        match acl::read_acls(path, metadata)
+            .ok()
+            .and_then(|acls| acls.access)
+        {
+            Some(acl) => {
+                let named_user = acl
+                    .entries
+                    .iter()
+                    .find(|entry| entry.tag == AclTag::User(uid));
+                let groups: Vec<_> = acl
+                    .entries
+                    .iter()
+                    .filter(|entry| match entry.tag {
+                        AclTag::GroupObj => in_group(metadata.gid()),
+                        AclTag::Group(id) => in_group(id),
+                        _ => false,
+                    })
+                    .collect();
+
+                // The first class that matches decides: named user, then any
+                // matching group entry, then others
+                if let Some(entry) = named_user {
+                    Access::from_bits(acl.effective(entry) as u32)
+                } else if !groups.is_empty() {
+                    let bits = groups
+                        .iter()
+                        .fold(0, |bits, entry| bits | acl.effective(entry));
+                    Access::from_bits(bits as u32)
+                } else {
+                    Access::from_bits(mode)
+                }
+            }
+            None if in_group(metadata.gid()) => Access::from_bits(mode >> 3),
+            None => Access::from_bits(mode),
+        }
+    };
+
+    if access.write && !is_writable_inode(path, metadata) {
+        access.write = false;
+    }
+    access
+}
+
+/// Writes are refused on read-only mounts and on immutable or append-only
+/// files, whatever the permissions say
+fn is_writable_inode(path: &Path, metadata: &Metadata) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } == 0
+        && stat.f_flag & libc::ST_RDONLY != 0
+    {
+        return false;
+    }
+
+    match inode_flags::read_inode_flags(path, metadata) {
+        Ok(Some(flags)) => !flags.is_immutable() && !flags.is_append_only(),
+        _ => true,
+    }
+}
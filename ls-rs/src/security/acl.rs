@@ -0,0 +1,222 @@
+use crate::security::xattr;
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+
+const ACL_XATTR_VERSION: u32 = 2;
+const ACL_ENTRY_SIZE: usize = 8;
+
+// Tags of the on-disk entries, from <linux/posix_acl.h>
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclTag {
+    UserObj,    // The owner
+    User(u32),  // A named user
+    GroupObj,   // The owning group
+    Group(u32), // A named group
+    Mask,       // Upper bound for named users and all groups
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AclEntry {
+    pub tag: AclTag,
+    pub perms: u8, // rwx bits: 4, 2, 1
+}
+
+impl AclEntry {
+    /// Whether the mask applies to this entry
+    fn is_masked(&self) -> bool {
+        matches!(
+            self.tag,
+            AclTag::User(_) | AclTag::GroupObj | AclTag::Group(_)
+        )
+    }
+}
+
+/// A decoded `system.posix_acl_access` or `system.posix_acl_default` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    pub entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    /// Decode the little-endian xattr format: a version header followed by
+    /// (tag, perms, id) entries
+    pub fn parse(value: &[u8]) -> Result<Self, AclError> {
+        if value.len() < 4
+            || !value[4..]
+                .chunks_exact(ACL_ENTRY_SIZE)
+                .remainder()
+                .is_empty()
+        {
+            return Err(AclError::Truncated);
+        }
+
+        let version = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+        if version != ACL_XATTR_VERSION {
+            return Err(AclError::UnsupportedVersion(version));
+        }
+
+        let entries = value[4..]
+            .chunks_exact(ACL_ENTRY_SIZE)
+            .map(|entry| {
+                let tag = u16::from_le_bytes([entry[0], entry[1]]);
+                let perms = u16::from_le_bytes([entry[2], entry[3]]);
+                let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+                let tag = match tag {
+                    ACL_USER_OBJ => AclTag::UserObj,
+                    ACL_USER => AclTag::User(id),
+                    ACL_GROUP_OBJ => AclTag::GroupObj,
+                    ACL_GROUP => AclTag::Group(id),
+                    ACL_MASK => AclTag::Mask,
+                    ACL_OTHER => AclTag::Other,
+                    other => return Err(AclError::UnknownTag(other)),
+                };
+                Ok(AclEntry {
+                    tag,
+                    perms: (perms & 0o7) as u8,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { entries })
+    }
+
+    pub fn mask(&self) -> Option<u8> {
+        self.entries
+            .iter()
+            .find(|entry| entry.tag == AclTag::Mask)
+            .map(|entry| entry.perms)
+    }
+
+    /// Permissions an entry actually grants once the mask is applied
+    pub fn effective(&self, entry: &AclEntry) -> u8 {
+        match self.mask() {
+            Some(mask) if entry.is_masked() => entry.perms & mask,
+            _ => entry.perms,
+        }
+    }
+}
+
+/// Access ACL and, for directories, default ACL of a file
+#[derive(Debug, Default, Clone)]
+pub struct FileAcls {
+    pub access: Option<Acl>,
+    pub default: Option<Acl>,
+}
+
+/// Read the ACLs of a path without following symlinks
+pub fn read_acls(path: &Path, metadata: &Metadata) -> Result<FileAcls, AclError> {
+    if metadata.file_type().is_symlink() {
+        return Ok(FileAcls::default());
+    }
+
+    let access = xattr::get_xattr(path, "system.posix_acl_access")?
+        .map(|value| Acl::parse(&value))
+        .transpose()?;
+    let default = if metadata.is_dir() {
+        xattr::get_xattr(path, "system.posix_acl_default")?
+            .map(|value| Acl::parse(&value))
+            .transpose()?
+    } else {
+        None
+    };
+
+    Ok(FileAcls { access, default })
+}
+
+/// Lines in `getfacl` notation, default ACL entries prefixed with `default:`,
+/// with the effective permissions noted where the mask narrows an entry
+pub fn format_acls(acls: &FileAcls) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (prefix, acl) in [("", &acls.access), ("default:", &acls.default)] {
+        let Some(acl) = acl else {
+            continue;
+        };
+
+        for entry in &acl.entries {
+            let text = format!(
+                "{}{}:{}",
+                prefix,
+                tag_string(&entry.tag),
+                perms_string(entry.perms)
+            );
+            let effective = acl.effective(entry);
+            if effective != entry.perms {
+                lines.push(format!(
+                    "\t{:<31} #effective:{}",
+                    text,
+                    perms_string(effective)
+                ));
+            } else {
+                lines.push(format!("\t{}", text));
+            }
+        }
+    }
+    lines
+}
+
+fn tag_string(tag: &AclTag) -> String {
+    match tag {
+        AclTag::UserObj => "user:".to_string(),
+        AclTag::User(uid) => format!(
+            "user:{}",
+            users::get_user_by_uid(*uid)
+                .map(|u| u.name().to_string_lossy().into_owned())
+                .unwrap_or_else(|| uid.to_string())
+        ),
+        AclTag::GroupObj => "group:".to_string(),
+        AclTag::Group(gid) => format!(
+            "group:{}",
+            users::get_group_by_gid(*gid)
+                .map(|g| g.name().to_string_lossy().into_owned())
+                .unwrap_or_else(|| gid.to_string())
+        ),
+        AclTag::Mask => "mask:".to_string(),
+        AclTag::Other => "other:".to_string(),
+    }
+}
+
+fn perms_string(perms: u8) -> String {
+    [(4, 'r'), (2, 'w'), (1, 'x')]
+        .iter()
+        .map(|(bit, c)| if perms & bit != 0 { *c } else { '-' })
+        .collect()
+}
+
+/// Error type for ACL decoding
+#[derive(Debug)]
+pub enum AclError {
+    Truncated,
+    UnsupportedVersion(u32),
+    UnknownTag(u16),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for AclError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AclError::Truncated => write!(f, "truncated ACL"),
+            AclError::UnsupportedVersion(version) => {
+                write!(f, "unsupported ACL version {}", version)
+            }
+            AclError::UnknownTag(tag) => write!(f, "unknown ACL entry tag {:#x}", tag),
+            AclError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AclError {}
+
+impl From<io::Error> for AclError {
+    fn from(e: io::Error) -> Self {
+        AclError::Io(e)
+    }
+}
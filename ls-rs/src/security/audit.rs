@@ -0,0 +1,329 @@
+use crate::security::permissions;
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Directories whose contents other users must not be able to change
+const SENSITIVE_PATHS: [&str; 11] = [
+    "/etc",
+    "/bin",
+    "/sbin",
+    "/usr/bin",
+    "/usr/sbin",
+    "/usr/lib",
+    "/usr/local/bin",
+    "/usr/local/sbin",
+    "/lib",
+    "/boot",
+    "/root",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Low => "\x1b[36m",        // Cyan
+            Severity::Medium => "\x1b[33m",     // Yellow
+            Severity::High => "\x1b[1;31m",     // Bold red
+            Severity::Critical => "\x1b[1;41m", // Bold on red
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    Setuid,
+    Setgid,
+    WorldWritable,    // World-writable file
+    WorldWritableDir, // World-writable directory without the sticky bit
+    NoOwner,          // UID without a passwd entry
+    NoGroup,          // GID without a group entry
+    GroupWritableSensitive,
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FindingKind::Setuid => "setuid",
+            FindingKind::Setgid => "setgid",
+            FindingKind::WorldWritable => "world-writable",
+            FindingKind::WorldWritableDir => "world-writable-dir",
+            FindingKind::NoOwner => "no-owner",
+            FindingKind::NoGroup => "no-group",
+            FindingKind::GroupWritableSensitive => "group-writable-sensitive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub kind: FindingKind,
+    pub severity: Severity,
+    pub mode: String, // Octal mode, e.g. 4755
+    pub detail: String,
+}
+
+/// How findings are written
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AuditFormat {
+    #[default]
+    Text, // One line per finding, highlighted by severity
+    Json, // One JSON object per line
+}
+
+/// Check a single entry; symlinks are skipped since their mode is unused
+pub fn audit_entry(path: &Path, metadata: &Metadata) -> Vec<Finding> {
+    if metadata.file_type().is_symlink() {
+        return Vec::new();
+    }
+
+    let mode = metadata.permissions().mode();
+    let is_dir = metadata.is_dir();
+    let executable = metadata.is_file() && mode & 0o111 != 0;
+    let sensitive = is_sensitive(path);
+    let mut findings = Vec::new();
+    let mut add = |kind, severity, detail: String| {
+        findings.push(Finding {
+            path: path.to_path_buf(),
+            kind,
+            severity,
+            mode: permissions::get_octal_mode(metadata),
+            detail,
+        })
+    };
+
+    if executable && mode & 0o4000 != 0 {
+        let owner = user_name(metadata.uid());
+        // Root-owned setuid binaries are the classic escalation path; a
+        // writable one is an immediate root shell
+        let severity = match (metadata.uid() == 0, mode & 0o022 != 0) {
+            (true, true) => Severity::Critical,
+            (true, false) => Severity::High,
+            (false, _) => Severity::Medium,
+        };
+        add(
+            FindingKind::Setuid,
+            severity,
+            format!("runs as user {}", owner),
+        );
+    }
+    if executable && mode & 0o2000 != 0 {
+        add(
+            FindingKind::Setgid,
+            Severity::Medium,
+            format!("runs as group {}", group_name(metadata.gid())),
+        );
+    }
+
+    if mode & 0o002 != 0 {
+        if is_dir && mode & 0o1000 == 0 {
+            add(
+                FindingKind::WorldWritableDir,
+                Severity::High,
+                "anyone can delete or replace its entries".to_string(),
+            );
+        } else if !is_dir {
+            add(
+                FindingKind::WorldWritable,
+                if sensitive {
+                    Severity::High
+                } else {
+                    Severity::Medium
+                },
+                "anyone can modify it".to_string(),
+            );
+        }
+    } else if sensitive && mode & 0o020 != 0 && !(is_dir && mode & 0o1000 != 0) {
+        add(
+            FindingKind::GroupWritableSensitive,
+            Severity::Medium,
+            format!(
+                "group {} can modify it in a system path",
+                group_name(metadata.gid())
+            ),
+        );
+    }
+
+    if users::get_user_by_uid(metadata.uid()).is_none() {
+        add(
+            FindingKind::NoOwner,
+            Severity::Medium,
+            format!(
+                "UID {} has no user; a new account may inherit it",
+                metadata.uid()
+            ),
+        );
+    }
+    if users::get_group_by_gid(metadata.gid()).is_none() {
+        add(
+            FindingKind::NoGroup,
+            Severity::Low,
+            format!("GID {} has no group", metadata.gid()),
+        );
+    }
+
+    findings
+}
+
+/// Findings of an audit, and how many entries below the audited path could
+/// not be read
+#[derive(Debug)]
+pub struct AuditReport {
+    pub findings: Vec<Finding>,
+    pub errors: usize,
+}
+
+/// Audit the entries of a directory, descending into subdirectories (but
+/// not through symlinks) if `recursive` is set. Hidden entries are always
+/// included. Unreadable directories and entries are reported to stderr,
+/// counted and skipped.
+pub fn audit_directory(path: &Path, recursive: bool) -> io::Result<AuditReport> {
+    let mut findings = Vec::new();
+    let mut errors = 0;
+    let mut pending = vec![path.to_path_buf()];
+    let mut skip = |target: &Path, e: io::Error| {
+        eprintln!("rust-ls: {}: {}", target.display(), e);
+        errors += 1;
+    };
+
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            // The top-level path is the caller's error; below it, keep going
+            Err(e) if dir == path => return Err(e),
+            Err(e) => {
+                skip(&dir, e);
+                continue;
+            }
+        };
+
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let entry_path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    skip(&dir, e);
+                    continue;
+                }
+            };
+            let metadata = match fs::symlink_metadata(&entry_path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    skip(&entry_path, e);
+                    continue;
+                }
+            };
+            findings.extend(audit_entry(&entry_path, &metadata));
+            if recursive && metadata.is_dir() {
+                subdirs.push(entry_path);
+            }
+        }
+
+        // Visit subdirectories in name order
+        subdirs.sort();
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(AuditReport { findings, errors })
+}
+
+/// Write findings, most severe first
+pub fn write_findings(
+    out: &mut impl Write,
+    findings: &mut [Finding],
+    format: AuditFormat,
+    color: bool,
+) -> io::Result<()> {
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.path.cmp(&b.path)));
+
+    for finding in findings.iter() {
+        match format {
+            AuditFormat::Text => {
+                let (start, end) = if color {
+                    (finding.severity.color(), "\x1b[0m")
+                } else {
+                    ("", "")
+                };
+                writeln!(
+                    out,
+                    "{}{:<8}{} {:<24} {} {}: {}",
+                    start,
+                    finding.severity.to_string().to_uppercase(),
+                    end,
+                    finding.kind,
+                    finding.mode,
+                    finding.path.display(),
+                    finding.detail
+                )?;
+            }
+            AuditFormat::Json => writeln!(
+                out,
+                "{{\"severity\":\"{}\",\"kind\":\"{}\",\"mode\":\"{}\",\"path\":\"{}\",\"detail\":\"{}\"}}",
+                finding.severity,
+                finding.kind,
+                finding.mode,
+                json_escape(&finding.path.to_string_lossy()),
+                json_escape(&finding.detail)
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+fn is_sensitive(path: &Path) -> bool {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    SENSITIVE_PATHS
+        .iter()
+        .any(|sensitive| path.starts_with(sensitive))
+}
+
+fn user_name(uid: u32) -> String {
+    users::get_user_by_uid(uid)
+        .map(|u| u.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| uid.to_string())
+}
+
+fn group_name(gid: u32) -> String {
+    users::get_group_by_gid(gid)
+        .map(|g| g.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| gid.to_string())
+}
+
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
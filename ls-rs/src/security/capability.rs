@@ -0,0 +1,213 @@
+use crate::security::xattr;
+use std::fmt;
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+
+const CAPABILITY_XATTR: &str = "security.capability";
+
+// Layout of struct vfs_cap_data, from <linux/capability.h>
+const VFS_CAP_REVISION_MASK: u32 = 0xff00_0000;
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+const VFS_CAP_REVISION_2: u32 = 0x0200_0000;
+const VFS_CAP_REVISION_3: u32 = 0x0300_0000;
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+/// Capability names in bit order, as printed by `getcap`
+const CAPABILITY_NAMES: [&str; 41] = [
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+/// Decoded `security.capability` xattr of an executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileCapabilities {
+    pub permitted: u64,
+    pub inheritable: u64,
+    pub effective: bool,     // Permitted capabilities are raised on exec
+    pub rootid: Option<u32>, // v3: root of the user namespace the caps apply to
+}
+
+impl FileCapabilities {
+    /// Decode a v1, v2 or v3 `vfs_cap_data` value
+    pub fn parse(value: &[u8]) -> Result<Self, CapabilityError> {
+        let word = |index: usize| -> Result<u32, CapabilityError> {
+            value
+                .get(index * 4..index * 4 + 4)
+                .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .ok_or(CapabilityError::Truncated)
+        };
+
+        let magic = word(0)?;
+        let effective = magic & VFS_CAP_FLAGS_EFFECTIVE != 0;
+        match magic & VFS_CAP_REVISION_MASK {
+            VFS_CAP_REVISION_1 => Ok(Self {
+                permitted: word(1)? as u64,
+                inheritable: word(2)? as u64,
+                effective,
+                rootid: None,
+            }),
+            revision @ (VFS_CAP_REVISION_2 | VFS_CAP_REVISION_3) => Ok(Self {
+                permitted: word(1)? as u64 | (word(3)? as u64) << 32,
+                inheritable: word(2)? as u64 | (word(4)? as u64) << 32,
+                effective,
+                rootid: if revision == VFS_CAP_REVISION_3 {
+                    Some(word(5)?)
+                } else {
+                    None
+                },
+            }),
+            revision => Err(CapabilityError::UnsupportedRevision(revision >> 24)),
+        }
+    }
+
+    /// Flags of one capability in `getcap` order: effective, inheritable, permitted
+    fn flags(&self, bit: usize) -> String {
+        let permitted = self.permitted & (1 << bit) != 0;
+        let inheritable = self.inheritable & (1 << bit) != 0;
+        let mut flags = String::new();
+        if self.effective && (permitted || inheritable) {
+            flags.push('e');
+        }
+        if inheritable {
+            flags.push('i');
+        }
+        if permitted {
+            flags.push('p');
+        }
+        flags
+    }
+}
+
+impl fmt::Display for FileCapabilities {
+    /// Capabilities with the same flags are grouped as `cap_a,cap_b=ep`; a
+    /// group holding every known capability is written without names
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        for bit in 0..64 {
+            let flags = self.flags(bit);
+            if flags.is_empty() {
+                continue;
+            }
+            match groups
+                .iter_mut()
+                .find(|(group_flags, _)| *group_flags == flags)
+            {
+                Some((_, bits)) => bits.push(bit),
+                None => groups.push((flags, vec![bit])),
+            }
+        }
+
+        // Like libcap, the first clause assigns and later ones add flags
+        let clauses: Vec<String> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, (flags, bits))| {
+                let operator = if i == 0 { '=' } else { '+' };
+                if (0..CAPABILITY_NAMES.len()).all(|bit| bits.contains(&bit)) {
+                    return format!("{}{}", operator, flags);
+                }
+                let names: Vec<String> = bits
+                    .iter()
+                    .map(|bit| match CAPABILITY_NAMES.get(*bit) {
+                        Some(name) => name.to_string(),
+                        None => format!("cap_{}", bit),
+                    })
+                    .collect();
+                format!("{}{}{}", names.join(","), operator, flags)
+            })
+            .collect();
+        write!(f, "{}", clauses.join(" "))?;
+
+        if let Some(rootid) = self.rootid.filter(|rootid| *rootid != 0) {
+            write!(f, " [rootid={}]", rootid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a regular file carries file capabilities
+pub fn has_capabilities(path: &Path, metadata: &Metadata) -> bool {
+    metadata.is_file() && xattr::has_xattr(path, CAPABILITY_XATTR)
+}
+
+/// Read the capabilities of a regular file, or `None` if it has none
+pub fn read_capabilities(
+    path: &Path,
+    metadata: &Metadata,
+) -> Result<Option<FileCapabilities>, CapabilityError> {
+    if !metadata.is_file() {
+        return Ok(None);
+    }
+
+    xattr::get_xattr(path, CAPABILITY_XATTR)?
+        .map(|value| FileCapabilities::parse(&value))
+        .transpose()
+}
+
+/// Error type for capability decoding
+#[derive(Debug)]
+pub enum CapabilityError {
+    Truncated,
+    UnsupportedRevision(u32),
+    Io(io::Error),
+}
+
+impl fmt::Display for CapabilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CapabilityError::Truncated => write!(f, "truncated capability set"),
+            CapabilityError::UnsupportedRevision(revision) => {
+                write!(f, "unsupported capability revision {}", revision)
+            }
+            CapabilityError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CapabilityError {}
+
+impl From<io::Error> for CapabilityError {
+    fn from(e: io::Error) -> Self {
+        CapabilityError::Io(e)
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::fs::{Metadata, OpenOptions};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+// Flags returned by FS_IOC_GETFLAGS, from <linux/fs.h>
+const FS_SECRM_FL: u32 = 0x0000_0001;
+const FS_UNRM_FL: u32 = 0x0000_0002;
+const FS_COMPR_FL: u32 = 0x0000_0004;
+const FS_SYNC_FL: u32 = 0x0000_0008;
+const FS_IMMUTABLE_FL: u32 = 0x0000_0010;
+const FS_APPEND_FL: u32 = 0x0000_0020;
+const FS_NODUMP_FL: u32 = 0x0000_0040;
+const FS_NOATIME_FL: u32 = 0x0000_0080;
+const FS_NOCOMP_FL: u32 = 0x0000_0400;
+const FS_ENCRYPT_FL: u32 = 0x0000_0800;
+const FS_INDEX_FL: u32 = 0x0000_1000;
+const FS_JOURNAL_DATA_FL: u32 = 0x0000_4000;
+const FS_NOTAIL_FL: u32 = 0x0000_8000;
+const FS_DIRSYNC_FL: u32 = 0x0001_0000;
+const FS_TOPDIR_FL: u32 = 0x0002_0000;
+const FS_EXTENT_FL: u32 = 0x0008_0000;
+const FS_VERITY_FL: u32 = 0x0010_0000;
+const FS_NOCOW_FL: u32 = 0x0080_0000;
+const FS_DAX_FL: u32 = 0x0200_0000;
+const FS_INLINE_DATA_FL: u32 = 0x1000_0000;
+const FS_PROJINHERIT_FL: u32 = 0x2000_0000;
+const FS_CASEFOLD_FL: u32 = 0x4000_0000;
+
+/// Flags with their letters, in the order `lsattr` prints them
+const FLAG_LETTERS: [(u32, char); 22] = [
+    (FS_SECRM_FL, 's'),
+    (FS_UNRM_FL, 'u'),
+    (FS_SYNC_FL, 'S'),
+    (FS_DIRSYNC_FL, 'D'),
+    (FS_IMMUTABLE_FL, 'i'),
+    (FS_APPEND_FL, 'a'),
+    (FS_NODUMP_FL, 'd'),
+    (FS_NOATIME_FL, 'A'),
+    (FS_COMPR_FL, 'c'),
+    (FS_ENCRYPT_FL, 'E'),
+    (FS_JOURNAL_DATA_FL, 'j'),
+    (FS_INDEX_FL, 'I'),
+    (FS_NOTAIL_FL, 't'),
+    (FS_TOPDIR_FL, 'T'),
+    (FS_EXTENT_FL, 'e'),
+    (FS_NOCOW_FL, 'C'),
+    (FS_DAX_FL, 'x'),
+    (FS_CASEFOLD_FL, 'F'),
+    (FS_INLINE_DATA_FL, 'N'),
+    (FS_PROJINHERIT_FL, 'P'),
+    (FS_VERITY_FL, 'V'),
+    (FS_NOCOMP_FL, 'm'),
+];
+
+/// Inode flags as set by `chattr`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InodeFlags(pub u32);
+
+impl InodeFlags {
+    pub fn contains(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    pub fn is_immutable(&self) -> bool {
+        self.contains(FS_IMMUTABLE_FL)
+    }
+
+    pub fn is_append_only(&self) -> bool {
+        self.contains(FS_APPEND_FL)
+    }
+}
+
+impl fmt::Display for InodeFlags {
+    /// Fixed-width letters like `lsattr`, e.g. `----i---------e-------`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, letter) in FLAG_LETTERS {
+            write!(f, "{}", if self.contains(flag) { letter } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+/// Width of the `lsattr` flag string
+pub const LSATTR_WIDTH: usize = FLAG_LETTERS.len();
+
+/// Read the inode flags of a regular file or directory. Returns `None` for
+/// other file types and for file systems that do not support the ioctl.
+pub fn read_inode_flags(path: &Path, metadata: &Metadata) -> io::Result<Option<InodeFlags>> {
+    // Like lsattr, never open devices, FIFOs or sockets, nor follow symlinks
+    if !metadata.is_file() && !metadata.is_dir() {
+        return Ok(None);
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(path)?;
+
+    // The kernel reads and writes an int despite the ioctl being declared long
+    let mut flags: libc::c_int = 0;
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+    if result < 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENOTTY) | Some(libc::EOPNOTSUPP) | Some(libc::EINVAL) => Ok(None),
+            _ => Err(err),
+        };
+    }
+
+    Ok(Some(InodeFlags(flags as u32)))
+}
@@ -1,2 +1,8 @@
+pub mod access;
+pub mod acl;
+pub mod audit;
+pub mod capability;
+pub mod inode_flags;
 pub mod permissions;
 pub mod selinux;
+pub mod xattr;
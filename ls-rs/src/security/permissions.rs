@@ -1,5 +1,26 @@
+use crate::security::xattr::has_xattr;
 use std::fs::Metadata;
 use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+
+/// Alternate access method marker that GNU ls appends to the mode string
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ModeIndicator {
+    #[default]
+    None,
+    SecurityContext, // '.': only an SELinux context
+    Acl,             // '+': a POSIX ACL, with or without a context
+}
+
+impl ModeIndicator {
+    pub fn as_char(&self) -> char {
+        match self {
+            ModeIndicator::None => ' ',
+            ModeIndicator::SecurityContext => '.',
+            ModeIndicator::Acl => '+',
+        }
+    }
+}
 
 pub fn get_mode_string(metadata: &Metadata) -> String {
     let mode = metadata.permissions().mode();
@@ -79,3 +100,123 @@ fn get_file_type_char(metadata: &Metadata) -> char {
         '-'
     }
 }
+
+/// Detect ACLs and SELinux contexts from the extended attributes of a path,
+/// without following symlinks
+pub fn get_mode_indicator(path: &Path, metadata: &Metadata) -> ModeIndicator {
+    // Symlinks cannot carry ACLs, and default ACLs only exist on directories.
+    // The kernel drops access ACLs that only mirror the mode bits.
+    let has_acl = !metadata.file_type().is_symlink()
+        && (has_xattr(path, "system.posix_acl_access")
+            || (metadata.is_dir() && has_xattr(path, "system.posix_acl_default")));
+
+    if has_acl {
+        ModeIndicator::Acl
+    } else if has_xattr(path, "security.selinux") {
+        ModeIndicator::SecurityContext
+    } else {
+        ModeIndicator::None
+    }
+}
+
+/// Permission and special bits in octal, e.g. `4755`
+pub fn get_octal_mode(metadata: &Metadata) -> String {
+    format!("{:04o}", metadata.permissions().mode() & 0o7777)
+}
+
+/// Spell out what the owner, the group and everyone else may do with an
+/// entry, followed by the effect of any setuid, setgid or sticky bit
+pub fn explain_mode(metadata: &Metadata, owner: &str, group: &str) -> Vec<String> {
+    if metadata.file_type().is_symlink() {
+        return vec!["symlink: its own permissions are not used".to_string()];
+    }
+
+    let mode = metadata.permissions().mode();
+    let is_dir = metadata.is_dir();
+    let sticky = mode & 0o1000 != 0;
+
+    let mut lines: Vec<String> = [
+        (format!("owner {}", owner), mode >> 6),
+        (format!("group {}", group), mode >> 3),
+        ("others".to_string(), mode),
+    ]
+    .iter()
+    .map(|(principal, bits)| {
+        let actions = if is_dir {
+            explain_dir_bits(bits & 0o7, sticky)
+        } else {
+            explain_file_bits(bits & 0o7)
+        };
+        format!("{}: {}", principal, actions)
+    })
+    .collect();
+
+    let executable = mode & 0o111 != 0;
+    if mode & 0o4000 != 0 {
+        lines.push(if is_dir {
+            "setuid: ignored on directories".to_string()
+        } else if executable {
+            format!("setuid: runs with the privileges of user {}", owner)
+        } else {
+            "setuid: no effect, nobody may execute it".to_string()
+        });
+    }
+    if mode & 0o2000 != 0 {
+        lines.push(if is_dir {
+            format!(
+                "setgid: new entries belong to group {}, new directories inherit setgid",
+                group
+            )
+        } else if mode & 0o010 != 0 {
+            format!("setgid: runs with the privileges of group {}", group)
+        } else {
+            "setgid: no effect without group execute".to_string()
+        });
+    }
+    if sticky {
+        lines.push(if is_dir {
+            "sticky: only an entry's owner, the directory owner or root may delete or rename it"
+                .to_string()
+        } else {
+            "sticky: ignored on files".to_string()
+        });
+    }
+
+    lines
+}
+
+fn explain_file_bits(bits: u32) -> String {
+    let actions: Vec<&str> = [(0o4, "read"), (0o2, "write"), (0o1, "execute")]
+        .iter()
+        .filter(|(bit, _)| bits & bit != 0)
+        .map(|(_, action)| *action)
+        .collect();
+    join_actions(&actions)
+}
+
+fn explain_dir_bits(bits: u32, sticky: bool) -> String {
+    let mut actions = Vec::new();
+    if bits & 0o4 != 0 {
+        actions.push("list entries");
+    }
+    if bits & 0o2 != 0 {
+        // Creating, removing and renaming all need search permission too
+        actions.push(match (bits & 0o1 != 0, sticky) {
+            (false, _) => "write (no effect without execute)",
+            (true, false) => "create, delete and rename entries",
+            (true, true) => "create entries, delete and rename their own",
+        });
+    }
+    if bits & 0o1 != 0 {
+        actions.push("enter and access entries by name");
+    }
+    join_actions(&actions)
+}
+
+fn join_actions(actions: &[&str]) -> String {
+    if actions.is_empty() {
+        "no access".to_string()
+    } else {
+        actions.join("; ")
+    }
+}
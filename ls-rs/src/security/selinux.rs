@@ -1,5 +1,6 @@
 use selinux::{self, KernelSupport, SecurityContext};
 use std::ffi::{CStr, CString};
+use std::fmt;
 use std::io;
 use std::path::Path;
 
@@ -8,12 +9,192 @@ pub struct SELinuxContext {
     pub user: String,
     pub role: String,
     pub type_: String,
-    pub range: String,
+    pub range: Option<MlsRange>,   // None on policies without MLS/MCS
+    pub raw_range: Option<String>, // A range not in sN:cN form, e.g. an mcstrans label
+}
+
+impl SELinuxContext {
+    /// Parse a raw `user:role:type[:range]` context. The range may itself
+    /// contain colons, e.g. `s0-s0:c0.c1023`, so only the first three
+    /// fields are split off. A range that does not parse as levels is kept
+    /// only as raw text.
+    pub fn parse(context: &str) -> Result<Self, &'static str> {
+        let mut parts = context.splitn(4, ':');
+        let (Some(user), Some(role), Some(type_)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("Invalid SELinux context format");
+        };
+        if user.is_empty() || role.is_empty() || type_.is_empty() {
+            return Err("Invalid SELinux context format");
+        }
+
+        let (range, raw_range) = match parts.next().map(|range| (range, MlsRange::parse(range))) {
+            Some((_, Ok(range))) => (Some(range), None),
+            Some((raw, Err(_))) => (None, Some(raw.to_string())),
+            None => (None, None),
+        };
+        Ok(SELinuxContext {
+            user: user.to_string(),
+            role: role.to_string(),
+            type_: type_.to_string(),
+            range,
+            raw_range,
+        })
+    }
+
+    /// The range as text, whether or not it parsed
+    pub fn range_text(&self) -> Option<String> {
+        self.range
+            .as_ref()
+            .map(|range| range.to_string())
+            .or_else(|| self.raw_range.clone())
+    }
+}
+
+impl fmt::Display for SELinuxContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.user, self.role, self.type_)?;
+        if let Some(range) = self.range_text() {
+            write!(f, ":{}", range)?;
+        }
+        Ok(())
+    }
+}
+
+/// MLS range: a low level and an optional high level (`s0-s0:c0.c1023`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsRange {
+    pub low: MlsLevel,
+    pub high: Option<MlsLevel>,
+}
+
+impl MlsRange {
+    fn parse(range: &str) -> Result<Self, &'static str> {
+        let (low, high) = match range.split_once('-') {
+            Some((low, high)) => (low, Some(high)),
+            None => (range, None),
+        };
+        Ok(MlsRange {
+            low: MlsLevel::parse(low)?,
+            high: high.map(MlsLevel::parse).transpose()?,
+        })
+    }
+}
+
+impl fmt::Display for MlsRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.low)?;
+        if let Some(high) = &self.high {
+            write!(f, "-{}", high)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sensitivity with its category set, e.g. `s0:c0.c255,c300`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlsLevel {
+    pub sensitivity: u32,
+    pub categories: Vec<CategoryRange>,
+}
+
+impl MlsLevel {
+    fn parse(level: &str) -> Result<Self, &'static str> {
+        let (sensitivity, categories) = match level.split_once(':') {
+            Some((sensitivity, categories)) => (sensitivity, Some(categories)),
+            None => (level, None),
+        };
+
+        let sensitivity = parse_numbered(sensitivity, 's').ok_or("Invalid MLS sensitivity")?;
+        let categories = match categories {
+            Some(categories) => categories
+                .split(',')
+                .map(CategoryRange::parse)
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(MlsLevel {
+            sensitivity,
+            categories,
+        })
+    }
+}
+
+impl fmt::Display for MlsLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s{}", self.sensitivity)?;
+        for (i, range) in self.categories.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { ':' } else { ',' }, range)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single category (`c5`, low == high) or an inclusive span (`c0.c1023`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CategoryRange {
+    pub low: u32,
+    pub high: u32,
+}
+
+impl CategoryRange {
+    fn parse(range: &str) -> Result<Self, &'static str> {
+        let invalid = "Invalid MLS category";
+        let (low, high) = match range.split_once('.') {
+            Some((low, high)) => (
+                parse_numbered(low, 'c').ok_or(invalid)?,
+                parse_numbered(high, 'c').ok_or(invalid)?,
+            ),
+            None => {
+                let category = parse_numbered(range, 'c').ok_or(invalid)?;
+                (category, category)
+            }
+        };
+        if low > high {
+            return Err(invalid);
+        }
+        Ok(CategoryRange { low, high })
+    }
+}
+
+impl fmt::Display for CategoryRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.low == self.high {
+            write!(f, "c{}", self.low)
+        } else {
+            write!(f, "c{}.c{}", self.low, self.high)
+        }
+    }
+}
+
+/// Parse `s0` or `c12` style identifiers
+fn parse_numbered(text: &str, prefix: char) -> Option<u32> {
+    text.strip_prefix(prefix)?.parse().ok()
+}
+
+/// A component of a context, selectable for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextField {
+    User,
+    Role,
+    Type,
+    Range,
+}
+
+impl ContextField {
+    pub const ALL: [ContextField; 4] = [
+        ContextField::User,
+        ContextField::Role,
+        ContextField::Type,
+        ContextField::Range,
+    ];
 }
 
 pub struct SELinuxConfig {
     pub enabled: bool,
     pub show_context: bool,
+    pub fields: Vec<ContextField>, // Components shown, in this order
     pub truncate_context: bool,
     pub max_context_width: Option<usize>,
 }
@@ -23,6 +204,7 @@ impl Default for SELinuxConfig {
         Self {
             enabled: selinux::kernel_support() != KernelSupport::Unsupported,
             show_context: false,
+            fields: ContextField::ALL.to_vec(),
             truncate_context: false,
             max_context_width: None,
         }
@@ -39,98 +221,119 @@ impl SELinuxHandler {
     }
 
     /// Get the SELinux context for a file
-    pub fn get_context(&self, path: &Path) -> io::Result<Option<SELinuxContext>> {
+    pub fn get_context(&self, path: &Path) -> Result<SELinuxContext, SELinuxError> {
         if !self.config.enabled {
-            return Ok(None);
+            return Err(SELinuxError::Disabled);
         }
 
-        match SecurityContext::of_path(path, true, true) {
+        // Like lgetfilecon, label symlinks themselves rather than their
+        // targets; the selinux crate already maps ENODATA to Ok(None)
+        match SecurityContext::of_path(path, false, true) {
             Ok(Some(context)) => match context.to_c_string() {
                 Ok(Some(context_str)) => self
                     .parse_context(&context_str)
-                    .map(Some)
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
-                Ok(None) => Ok(None),
-                Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+                    .map_err(|_| SELinuxError::InvalidContext),
+                Ok(None) => Err(SELinuxError::NotFound),
+                Err(e) => Err(SELinuxError::from(e)),
             },
-            Ok(None) => Ok(None),
-            Err(_) => Ok(None),
+            Ok(None) => Err(SELinuxError::NotFound),
+            Err(e) => Err(SELinuxError::from(e)),
         }
     }
 
     /// Format the context string for display
     pub fn format_context(&self, context: &SELinuxContext) -> String {
-        let full_context = format!(
-            "{}:{}:{}:{}",
-            context.user, context.role, context.type_, context.range
-        );
-
-        if let Some(max_width) = self.config.max_context_width {
-            if self.config.truncate_context && full_context.len() > max_width {
-                format!("{}...", &full_context[..max_width.saturating_sub(3)])
-            } else {
-                full_context
+        // Contexts without an MLS range simply have no range field
+        let fields: Vec<String> = self
+            .config
+            .fields
+            .iter()
+            .filter_map(|field| match field {
+                ContextField::User => Some(context.user.clone()),
+                ContextField::Role => Some(context.role.clone()),
+                ContextField::Type => Some(context.type_.clone()),
+                ContextField::Range => context.range_text(),
+            })
+            .collect();
+        let full_context = fields.join(":");
+
+        match self.config.max_context_width {
+            Some(max_width)
+                if self.config.truncate_context && full_context.chars().count() > max_width =>
+            {
+                let kept: String = full_context
+                    .chars()
+                    .take(max_width.saturating_sub(3))
+                    .collect();
+                format!("{}...", kept)
             }
-        } else {
-            full_context
+            _ => full_context,
         }
     }
 
     /// Parse a context string into its components
     fn parse_context(&self, context_str: &CStr) -> Result<SELinuxContext, &'static str> {
-        let context_str = context_str.to_string_lossy();
-        let parts: Vec<&str> = context_str.split(':').collect();
-        if parts.len() != 4 {
-            return Err("Invalid SELinux context format");
-        }
-
-        Ok(SELinuxContext {
-            user: parts[0].to_string(),
-            role: parts[1].to_string(),
-            type_: parts[2].to_string(),
-            range: parts[3].to_string(),
-        })
+        SELinuxContext::parse(&context_str.to_string_lossy())
     }
 
     /// Check if a path has a valid SELinux context
     pub fn has_context(&self, path: &Path) -> bool {
-        if !self.config.enabled {
-            return false;
-        }
-
-        SecurityContext::of_path(path, true, true).is_ok()
+        self.get_context(path).is_ok()
     }
 
     /// Get the context field width for alignment
-    pub fn get_context_width(&self, contexts: &[Option<SELinuxContext>]) -> usize {
-        if !self.config.enabled || !self.config.show_context {
+    pub fn get_context_width(&self, contexts: &[Result<SELinuxContext, SELinuxError>]) -> usize {
+        if !self.config.show_context {
             return 0;
         }
 
         contexts
             .iter()
-            .filter_map(|c| c.as_ref())
-            .map(|c| self.format_context(c).len())
+            .map(|c| self.display_context(c).chars().count())
             .max()
             .unwrap_or(0)
     }
+
+    /// Text shown for a context lookup; like GNU ls, `?` stands in for
+    /// entries without a usable context
+    pub fn display_context(&self, context: &Result<SELinuxContext, SELinuxError>) -> String {
+        match context {
+            Ok(context) => self.format_context(context),
+            Err(_) => "?".to_string(),
+        }
+    }
 }
 
 /// Error type for SELinux operations
 #[derive(Debug)]
 pub enum SELinuxError {
-    Disabled,
-    NotFound,
-    AccessDenied,
-    InvalidContext,
+    Disabled,       // No SELinux support in the kernel
+    NotFound,       // ENODATA: the file has no context
+    NotSupported,   // EOPNOTSUPP: the file system does not store contexts
+    AccessDenied,   // EACCES or EPERM
+    InvalidContext, // Not user:role:type[:range]
     Other(String),
 }
 
+impl SELinuxError {
+    /// Whether the error only means there is no context to show, rather
+    /// than a failure worth reporting
+    pub fn is_absent(&self) -> bool {
+        matches!(
+            self,
+            SELinuxError::Disabled | SELinuxError::NotFound | SELinuxError::NotSupported
+        )
+    }
+}
+
 impl std::fmt::Display for SELinuxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SELinuxError::Disabled => write!(f, "SELinux is disabled"),
             SELinuxError::NotFound => write!(f, "SELinux context not found"),
+            SELinuxError::NotSupported => {
+                write!(f, "SELinux contexts not supported by the file system")
+            }
             SELinuxError::AccessDenied => write!(f, "Access denied to SELinux context"),
             SELinuxError::InvalidContext => write!(f, "Invalid SELinux context"),
             SELinuxError::Other(msg) => write!(f, "SELinux error: {}", msg),
@@ -140,13 +343,31 @@ impl std::fmt::Display for SELinuxError {
 
 impl std::error::Error for SELinuxError {}
 
+impl From<selinux::errors::Error> for SELinuxError {
+    fn from(e: selinux::errors::Error) -> Self {
+        let errno = std::error::Error::source(&e)
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .and_then(|source| source.raw_os_error());
+        match errno {
+            Some(libc::ENODATA) => SELinuxError::NotFound,
+            Some(libc::EOPNOTSUPP) => SELinuxError::NotSupported,
+            Some(libc::EACCES) | Some(libc::EPERM) => SELinuxError::AccessDenied,
+            _ => SELinuxError::Other(e.to_string()),
+        }
+    }
+}
+
 // Integration with the FileInfo struct
 pub trait SELinuxExt {
-    fn get_selinux_context(&self, handler: &SELinuxHandler) -> io::Result<Option<SELinuxContext>>;
+    fn get_selinux_context(&self, handler: &SELinuxHandler)
+        -> Result<SELinuxContext, SELinuxError>;
 }
 
 impl SELinuxExt for crate::core::filesystem::FileInfo {
-    fn get_selinux_context(&self, handler: &SELinuxHandler) -> io::Result<Option<SELinuxContext>> {
+    fn get_selinux_context(
+        &self,
+        handler: &SELinuxHandler,
+    ) -> Result<SELinuxContext, SELinuxError> {
         handler.get_context(Path::new(&self.path))
     }
 }
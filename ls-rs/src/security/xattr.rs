@@ -0,0 +1,188 @@
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Extended attribute namespaces that can be selected for listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XattrNamespace {
+    User,
+    Trusted,
+    Security,
+    System,
+}
+
+impl XattrNamespace {
+    fn prefix(&self) -> &'static str {
+        match self {
+            XattrNamespace::User => "user.",
+            XattrNamespace::Trusted => "trusted.",
+            XattrNamespace::Security => "security.",
+            XattrNamespace::System => "system.",
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        name.starts_with(self.prefix())
+    }
+}
+
+/// How attribute values are shown below their names
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum XattrDump {
+    #[default]
+    None,
+    Hex,  // Offset, hex bytes and printable characters, 16 bytes per line
+    Text, // Value as text, with non-printable bytes escaped
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct XattrConfig {
+    pub namespaces: Vec<XattrNamespace>, // Empty selects every namespace
+    pub dump: XattrDump,
+}
+
+impl XattrConfig {
+    fn selects(&self, name: &str) -> bool {
+        self.namespaces.is_empty() || self.namespaces.iter().any(|ns| ns.contains(name))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Xattr {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+/// Whether a path carries the attribute `name`, without following symlinks
+pub fn has_xattr(path: &Path, name: &str) -> bool {
+    let (Ok(path), Ok(name)) = (path_cstring(path), CString::new(name)) else {
+        return false;
+    };
+
+    let size = unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+    size >= 0
+}
+
+/// Read the value of the attribute `name`, or `None` if the path does not
+/// carry it, without following symlinks
+pub fn get_xattr(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    let c_path = path_cstring(path)?;
+    let c_name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    match read_buffer(|buf, size| unsafe {
+        libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buf, size)
+    }) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENODATA) | Some(libc::ENOTSUP)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read the attributes of a path selected by `config`, without following
+/// symlinks. File systems without xattr support have none.
+pub fn list_xattrs(path: &Path, config: &XattrConfig) -> io::Result<Vec<Xattr>> {
+    let c_path = path_cstring(path)?;
+    let names = match read_buffer(|buf, size| unsafe {
+        libc::llistxattr(c_path.as_ptr(), buf as *mut libc::c_char, size)
+    }) {
+        Ok(names) => names,
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut xattrs: Vec<Xattr> = names
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let name = String::from_utf8_lossy(name).into_owned();
+            if !config.selects(&name) {
+                return None;
+            }
+
+            // Attributes can vanish or be unreadable between the two calls
+            let c_name = CString::new(name.as_str()).ok()?;
+            let value = read_buffer(|buf, size| unsafe {
+                libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buf, size)
+            })
+            .ok()?;
+            Some(Xattr { name, value })
+        })
+        .collect();
+
+    xattrs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(xattrs)
+}
+
+/// Lines shown under an entry's long-format line: name and value size, then
+/// the value itself if a dump format is selected
+pub fn format_xattrs(xattrs: &[Xattr], dump: XattrDump) -> Vec<String> {
+    let mut lines = Vec::new();
+    for xattr in xattrs {
+        lines.push(format!("\t{}\t{:>4}", xattr.name, xattr.value.len()));
+        match dump {
+            XattrDump::None => {}
+            XattrDump::Hex => lines.extend(hex_dump(&xattr.value)),
+            XattrDump::Text => lines.push(format!("\t\t{}", text_dump(&xattr.value))),
+        }
+    }
+    lines
+}
+
+fn hex_dump(value: &[u8]) -> Vec<String> {
+    value
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let text: String = chunk
+                .iter()
+                .map(|b| {
+                    if b.is_ascii_graphic() || *b == b' ' {
+                        *b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("\t\t{:04x}  {:<47}  |{}|", i * 16, hex.join(" "), text)
+        })
+        .collect()
+}
+
+fn text_dump(value: &[u8]) -> String {
+    // Values set from C strings, such as SELinux contexts, end with a NUL
+    let value = value.strip_suffix(&[0]).unwrap_or(value);
+    value
+        .iter()
+        .flat_map(|b| std::ascii::escape_default(*b))
+        .map(char::from)
+        .collect()
+}
+
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Call a size-querying xattr function until the buffer holds its result,
+/// retrying if the value grows in between
+fn read_buffer(mut read: impl FnMut(*mut libc::c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let size = read(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buf = vec![0u8; size as usize];
+        let read_size = read(buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+        if read_size >= 0 {
+            buf.truncate(read_size as usize);
+            return Ok(buf);
+        }
+
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ERANGE) {
+            return Err(err);
+        }
+    }
+}